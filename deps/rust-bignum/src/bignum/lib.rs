@@ -8,7 +8,8 @@
 extern crate gmp;
 extern crate num;
 
-use gmp::{Mpz, RandState};
+use gmp::Mpz;
+use std::cmp::min;
 use std::fmt;
 use std::from_str::FromStr;
 use std::num::{One, Zero, ToStrRadix};
@@ -152,6 +153,19 @@ impl BitAnd<BigUint, BigUint> for BigUint {
     }
 }
 
+impl BitOr<BigUint, BigUint> for BigUint {
+    fn bitor(&self, other: &BigUint) -> BigUint {
+        BigUint{ data: self.data.bitor(&other.data) }
+    }
+}
+
+impl Shl<uint, BigUint> for BigUint {
+    fn shl(&self, rhs: &uint) -> BigUint {
+        let shift = *rhs as c_ulong;
+        BigUint{ data: self.data.shl(&shift) }
+    }
+}
+
 impl Shr<uint, BigUint> for BigUint {
     fn shr(&self, rhs: &uint) -> BigUint {
         let shift = *rhs as c_ulong;
@@ -318,27 +332,33 @@ pub trait RandBigInt {
 
 impl<R: Rng> RandBigInt for R {
     fn gen_biguint(&mut self, bit_size: uint) -> BigUint {
-        let mut state = RandState::new();
-        let seed: c_ulong = self.gen();
-        state.seed_ui(seed);
-        let mpz = state.urandom_2exp(bit_size as c_ulong);
-        BigUint{ data: mpz }
+        // Every bit is drawn from the generator itself. Seeding a GMP
+        // RandState from it would limit the result to one word of entropy
+        let mut data: Mpz = Zero::zero();
+        let mut remaining = bit_size;
+        while remaining > 0 {
+            let bits = min(remaining, 32);
+            let word: u32 = self.gen();
+            let word: Mpz = FromPrimitive::from_u32(word >> ((32 - bits) as u32)).unwrap();
+            data = (data << (bits as c_ulong)) | word;
+            remaining -= bits;
+        }
+        BigUint{ data: data }
     }
 
     fn gen_biguint_below(&mut self, bound: &BigUint) -> BigUint {
-        // FIXME Add assertion once is_zero is implemented
-        // assert!(!bound.is_zero());
+        assert!(!bound.is_zero());
 
-        let mut state = RandState::new();
-        let seed: c_ulong = self.gen();
-        state.seed_ui(seed);
-        let mpz = state.urandom(&bound.data);
-        BigUint{ data: mpz }
+        // Rejection sampling keeps the result uniform
+        let bits = bound.bits();
+        loop {
+            let n = self.gen_biguint(bits);
+            if n < *bound { return n }
+        }
     }
 
     fn gen_biguint_range(&mut self, lbound: &BigUint, ubound: &BigUint) -> BigUint {
-        // FIXME Add assertion once cmp::Ord is implemented
-        // assert!(lbound < ubound);
+        assert!(*lbound < *ubound);
 
         return *lbound + self.gen_biguint_below(&(*ubound - *lbound));
     }
@@ -484,6 +504,26 @@ mod test_biguint {
         assert_eq!(two.bitand(&three), two);
     }
 
+    #[test]
+    fn test_bitor() {
+        let one: BigUint = FromPrimitive::from_uint(1).unwrap();
+        let two: BigUint = FromPrimitive::from_uint(2).unwrap();
+        let three: BigUint = FromPrimitive::from_uint(3).unwrap();
+
+        assert_eq!(one | two, three);
+        assert_eq!(two.bitor(&three), three);
+    }
+
+    #[test]
+    fn test_shl() {
+        let one: BigUint = FromPrimitive::from_uint(1).unwrap();
+        let two: BigUint = FromPrimitive::from_uint(2).unwrap();
+
+        assert_eq!(one << 1, two);
+        assert_eq!(one.shl(&1), two);
+        assert_eq!((one << 100).bits(), 101);
+    }
+
     #[test]
     fn test_shr() {
         let one: BigUint = FromPrimitive::from_uint(1).unwrap();
@@ -538,6 +578,15 @@ mod test_biguint {
         assert!(rand1.to_str() != rand2.to_str());
     }
 
+    #[test]
+    fn test_rand_gen_biguint_bits() {
+        let mut rng = task_rng();
+        // Sizes that aren't a whole number of words are never too long, and
+        // all of the bits are random
+        for _ in range(0, 100) { assert!(rng.gen_biguint(75).bits() <= 75); }
+        assert!(range(0, 100).any(|_| rng.gen_biguint(200).bits() > 190));
+    }
+
     #[test]
     fn test_rand_gen_biguint_below() {
         let mut rng = task_rng();
@@ -6,9 +6,9 @@ fn main() {
   //-> '{Some 1024-bit prime number} is prime' is a true statement!
 
   let message = ~"Some super secret scary message that I don't want anybody to see!";
-  let (public_key, private_key) = rsa::gen_keys_default();
-  let encrypted = public_key.encrypt(message);
+  let (public_key, private_key) = rsa::gen_keys_default().unwrap();
+  let encrypted = public_key.encrypt(message).unwrap();
   println!("The secret message is hidden inside of '{}'", encrypted); //-> A bunch of hex
-  let decrypted = private_key.decrypt(encrypted);
+  let decrypted = private_key.decrypt(encrypted).unwrap();
   println!("But we can get it out! It is '{}'", decrypted); //-> super secret scary message
 }
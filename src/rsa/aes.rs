@@ -0,0 +1,301 @@
+use std::slice;
+
+/// Size of an AES block in bytes
+pub static BLOCK_LEN: uint = 16;
+
+static SBOX: [u8, ..256] = [
+  0x63, 0x7c, 0x77, 0x7b, 0xf2, 0x6b, 0x6f, 0xc5, 0x30, 0x01, 0x67, 0x2b, 0xfe, 0xd7, 0xab, 0x76,
+  0xca, 0x82, 0xc9, 0x7d, 0xfa, 0x59, 0x47, 0xf0, 0xad, 0xd4, 0xa2, 0xaf, 0x9c, 0xa4, 0x72, 0xc0,
+  0xb7, 0xfd, 0x93, 0x26, 0x36, 0x3f, 0xf7, 0xcc, 0x34, 0xa5, 0xe5, 0xf1, 0x71, 0xd8, 0x31, 0x15,
+  0x04, 0xc7, 0x23, 0xc3, 0x18, 0x96, 0x05, 0x9a, 0x07, 0x12, 0x80, 0xe2, 0xeb, 0x27, 0xb2, 0x75,
+  0x09, 0x83, 0x2c, 0x1a, 0x1b, 0x6e, 0x5a, 0xa0, 0x52, 0x3b, 0xd6, 0xb3, 0x29, 0xe3, 0x2f, 0x84,
+  0x53, 0xd1, 0x00, 0xed, 0x20, 0xfc, 0xb1, 0x5b, 0x6a, 0xcb, 0xbe, 0x39, 0x4a, 0x4c, 0x58, 0xcf,
+  0xd0, 0xef, 0xaa, 0xfb, 0x43, 0x4d, 0x33, 0x85, 0x45, 0xf9, 0x02, 0x7f, 0x50, 0x3c, 0x9f, 0xa8,
+  0x51, 0xa3, 0x40, 0x8f, 0x92, 0x9d, 0x38, 0xf5, 0xbc, 0xb6, 0xda, 0x21, 0x10, 0xff, 0xf3, 0xd2,
+  0xcd, 0x0c, 0x13, 0xec, 0x5f, 0x97, 0x44, 0x17, 0xc4, 0xa7, 0x7e, 0x3d, 0x64, 0x5d, 0x19, 0x73,
+  0x60, 0x81, 0x4f, 0xdc, 0x22, 0x2a, 0x90, 0x88, 0x46, 0xee, 0xb8, 0x14, 0xde, 0x5e, 0x0b, 0xdb,
+  0xe0, 0x32, 0x3a, 0x0a, 0x49, 0x06, 0x24, 0x5c, 0xc2, 0xd3, 0xac, 0x62, 0x91, 0x95, 0xe4, 0x79,
+  0xe7, 0xc8, 0x37, 0x6d, 0x8d, 0xd5, 0x4e, 0xa9, 0x6c, 0x56, 0xf4, 0xea, 0x65, 0x7a, 0xae, 0x08,
+  0xba, 0x78, 0x25, 0x2e, 0x1c, 0xa6, 0xb4, 0xc6, 0xe8, 0xdd, 0x74, 0x1f, 0x4b, 0xbd, 0x8b, 0x8a,
+  0x70, 0x3e, 0xb5, 0x66, 0x48, 0x03, 0xf6, 0x0e, 0x61, 0x35, 0x57, 0xb9, 0x86, 0xc1, 0x1d, 0x9e,
+  0xe1, 0xf8, 0x98, 0x11, 0x69, 0xd9, 0x8e, 0x94, 0x9b, 0x1e, 0x87, 0xe9, 0xce, 0x55, 0x28, 0xdf,
+  0x8c, 0xa1, 0x89, 0x0d, 0xbf, 0xe6, 0x42, 0x68, 0x41, 0x99, 0x2d, 0x0f, 0xb0, 0x54, 0xbb, 0x16
+];
+static INV_SBOX: [u8, ..256] = [
+  0x52, 0x09, 0x6a, 0xd5, 0x30, 0x36, 0xa5, 0x38, 0xbf, 0x40, 0xa3, 0x9e, 0x81, 0xf3, 0xd7, 0xfb,
+  0x7c, 0xe3, 0x39, 0x82, 0x9b, 0x2f, 0xff, 0x87, 0x34, 0x8e, 0x43, 0x44, 0xc4, 0xde, 0xe9, 0xcb,
+  0x54, 0x7b, 0x94, 0x32, 0xa6, 0xc2, 0x23, 0x3d, 0xee, 0x4c, 0x95, 0x0b, 0x42, 0xfa, 0xc3, 0x4e,
+  0x08, 0x2e, 0xa1, 0x66, 0x28, 0xd9, 0x24, 0xb2, 0x76, 0x5b, 0xa2, 0x49, 0x6d, 0x8b, 0xd1, 0x25,
+  0x72, 0xf8, 0xf6, 0x64, 0x86, 0x68, 0x98, 0x16, 0xd4, 0xa4, 0x5c, 0xcc, 0x5d, 0x65, 0xb6, 0x92,
+  0x6c, 0x70, 0x48, 0x50, 0xfd, 0xed, 0xb9, 0xda, 0x5e, 0x15, 0x46, 0x57, 0xa7, 0x8d, 0x9d, 0x84,
+  0x90, 0xd8, 0xab, 0x00, 0x8c, 0xbc, 0xd3, 0x0a, 0xf7, 0xe4, 0x58, 0x05, 0xb8, 0xb3, 0x45, 0x06,
+  0xd0, 0x2c, 0x1e, 0x8f, 0xca, 0x3f, 0x0f, 0x02, 0xc1, 0xaf, 0xbd, 0x03, 0x01, 0x13, 0x8a, 0x6b,
+  0x3a, 0x91, 0x11, 0x41, 0x4f, 0x67, 0xdc, 0xea, 0x97, 0xf2, 0xcf, 0xce, 0xf0, 0xb4, 0xe6, 0x73,
+  0x96, 0xac, 0x74, 0x22, 0xe7, 0xad, 0x35, 0x85, 0xe2, 0xf9, 0x37, 0xe8, 0x1c, 0x75, 0xdf, 0x6e,
+  0x47, 0xf1, 0x1a, 0x71, 0x1d, 0x29, 0xc5, 0x89, 0x6f, 0xb7, 0x62, 0x0e, 0xaa, 0x18, 0xbe, 0x1b,
+  0xfc, 0x56, 0x3e, 0x4b, 0xc6, 0xd2, 0x79, 0x20, 0x9a, 0xdb, 0xc0, 0xfe, 0x78, 0xcd, 0x5a, 0xf4,
+  0x1f, 0xdd, 0xa8, 0x33, 0x88, 0x07, 0xc7, 0x31, 0xb1, 0x12, 0x10, 0x59, 0x27, 0x80, 0xec, 0x5f,
+  0x60, 0x51, 0x7f, 0xa9, 0x19, 0xb5, 0x4a, 0x0d, 0x2d, 0xe5, 0x7a, 0x9f, 0x93, 0xc9, 0x9c, 0xef,
+  0xa0, 0xe0, 0x3b, 0x4d, 0xae, 0x2a, 0xf5, 0xb0, 0xc8, 0xeb, 0xbb, 0x3c, 0x83, 0x53, 0x99, 0x61,
+  0x17, 0x2b, 0x04, 0x7e, 0xba, 0x77, 0xd6, 0x26, 0xe1, 0x69, 0x14, 0x63, 0x55, 0x21, 0x0c, 0x7d
+];
+
+// Round constants for the key schedule
+static RCON: [u8, ..10] = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x40, 0x80, 0x1b, 0x36];
+
+/// The AES block cipher with a 128, 192 or 256-bit key
+pub struct Aes {
+  priv round_keys: ~[u8],
+  priv rounds: uint
+}
+
+impl Aes {
+  /// AES with the given key, or `None` if the key isn't 16, 24 or 32 bytes
+  pub fn new(key: &[u8]) -> Option<Aes> {
+    if key.len() != 16 && key.len() != 24 && key.len() != 32 { return None }
+
+    // Key expansion from FIPS 197, working on 4 byte words
+    let nk = key.len() / 4;
+    let rounds = nk + 6;
+    let words = 4 * (rounds + 1);
+    let mut w = slice::with_capacity(4 * words);
+    w.push_all(key);
+    for i in range(nk, words) {
+      let mut t = [w[4 * i - 4], w[4 * i - 3], w[4 * i - 2], w[4 * i - 1]];
+      if i % nk == 0 {
+        t = [SBOX[t[1] as uint] ^ RCON[i / nk - 1], SBOX[t[2] as uint], SBOX[t[3] as uint],
+             SBOX[t[0] as uint]];
+      } else if nk > 6 && i % nk == 4 {
+        t = [SBOX[t[0] as uint], SBOX[t[1] as uint], SBOX[t[2] as uint], SBOX[t[3] as uint]];
+      }
+      for j in range(0, 4) {
+        let byte = w[4 * (i - nk) + j] ^ t[j];
+        w.push(byte);
+      }
+    }
+    Some(Aes{ round_keys: w, rounds: rounds })
+  }
+
+  fn round_key<'a>(&'a self, round: uint) -> &'a [u8] {
+    self.round_keys.slice(BLOCK_LEN * round, BLOCK_LEN * (round + 1))
+  }
+
+  /// Encrypt a single 16 byte block in place
+  pub fn encrypt_block(&self, block: &mut [u8]) {
+    add_round_key(block, self.round_key(0));
+    for round in range(1, self.rounds) {
+      sub_bytes(block, &SBOX);
+      shift_rows(block);
+      mix_columns(block);
+      add_round_key(block, self.round_key(round));
+    }
+    sub_bytes(block, &SBOX);
+    shift_rows(block);
+    add_round_key(block, self.round_key(self.rounds));
+  }
+
+  /// Decrypt a single 16 byte block in place
+  pub fn decrypt_block(&self, block: &mut [u8]) {
+    add_round_key(block, self.round_key(self.rounds));
+    for i in range(1, self.rounds) {
+      inv_shift_rows(block);
+      sub_bytes(block, &INV_SBOX);
+      add_round_key(block, self.round_key(self.rounds - i));
+      inv_mix_columns(block);
+    }
+    inv_shift_rows(block);
+    sub_bytes(block, &INV_SBOX);
+    add_round_key(block, self.round_key(0));
+  }
+
+  /// CBC mode encryption of `data` with PKCS#7 padding, so the output is always
+  /// a whole number of blocks
+  /// `iv` must be exactly one block long
+  pub fn cbc_encrypt(&self, iv: &[u8], data: &[u8]) -> ~[u8] {
+    assert_eq!(iv.len(), BLOCK_LEN);
+    let pad = BLOCK_LEN - data.len() % BLOCK_LEN;
+    let mut out = slice::with_capacity(data.len() + pad);
+    out.push_all(data);
+    out.grow(pad, &(pad as u8));
+
+    let mut prev = iv.to_owned();
+    for block in out.mut_chunks(BLOCK_LEN) {
+      for (byte, &chain) in block.mut_iter().zip(prev.iter()) { *byte ^= chain; }
+      self.encrypt_block(block);
+      prev = block.to_owned();
+    }
+    out
+  }
+
+  /// CBC mode decryption of `data`, removing the PKCS#7 padding
+  /// Returns `None` if `data` isn't a whole number of blocks or the padding is malformed
+  pub fn cbc_decrypt(&self, iv: &[u8], data: &[u8]) -> Option<~[u8]> {
+    assert_eq!(iv.len(), BLOCK_LEN);
+    if data.is_empty() || data.len() % BLOCK_LEN != 0 { return None }
+
+    let mut out = data.to_owned();
+    for (i, block) in out.mut_chunks(BLOCK_LEN).enumerate() {
+      let prev = if i == 0 { iv } else { data.slice(BLOCK_LEN * (i - 1), BLOCK_LEN * i) };
+      self.decrypt_block(block);
+      for (byte, &chain) in block.mut_iter().zip(prev.iter()) { *byte ^= chain; }
+    }
+
+    let pad = out[out.len() - 1] as uint;
+    if pad == 0 || pad > BLOCK_LEN { return None }
+    if out.slice_from(out.len() - pad).iter().any(|&byte| byte as uint != pad) { return None }
+    let len = out.len() - pad;
+    out.truncate(len);
+    Some(out)
+  }
+}
+
+impl Aes {
+  /// CTR mode encryption or decryption of `data`, starting from the counter block
+  /// `iv` and incrementing it as a single 128-bit big-endian integer
+  pub fn ctr_xor(&self, iv: &[u8], data: &[u8]) -> ~[u8] {
+    assert_eq!(iv.len(), BLOCK_LEN);
+    let mut counter = iv.to_owned();
+    let mut out = slice::with_capacity(data.len());
+    for chunk in data.chunks(BLOCK_LEN) {
+      let mut keystream = counter.clone();
+      self.encrypt_block(keystream);
+      for (&byte, &k) in chunk.iter().zip(keystream.iter()) { out.push(byte ^ k); }
+
+      let mut i = BLOCK_LEN;
+      while i > 0 {
+        i -= 1;
+        counter[i] += 1;
+        if counter[i] != 0 { break }
+      }
+    }
+    out
+  }
+}
+
+fn add_round_key(block: &mut [u8], key: &[u8]) {
+  for (byte, &k) in block.mut_iter().zip(key.iter()) { *byte ^= k; }
+}
+
+fn sub_bytes(block: &mut [u8], sbox: &[u8, ..256]) {
+  for byte in block.mut_iter() { *byte = sbox[*byte as uint]; }
+}
+
+// The state is stored column by column, so row `r` is bytes r, r + 4, r + 8 and r + 12
+fn shift_rows(block: &mut [u8]) {
+  let state = block.to_owned();
+  for r in range(1u, 4) {
+    for c in range(0u, 4) { block[r + 4 * c] = state[r + 4 * ((c + r) % 4)]; }
+  }
+}
+
+fn inv_shift_rows(block: &mut [u8]) {
+  let state = block.to_owned();
+  for r in range(1u, 4) {
+    for c in range(0u, 4) { block[r + 4 * ((c + r) % 4)] = state[r + 4 * c]; }
+  }
+}
+
+// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1
+fn gf_mul(a: u8, b: u8) -> u8 {
+  let mut a = a;
+  let mut b = b;
+  let mut product = 0u8;
+  while b != 0 {
+    if b & 1 != 0 { product ^= a; }
+    a = if a & 0x80 != 0 { (a << 1) ^ 0x1b } else { a << 1 };
+    b >>= 1;
+  }
+  product
+}
+
+// Multiplies every column by the matrix whose first row is `m`, rotating it for each following row
+fn mix_columns_with(block: &mut [u8], m: [u8, ..4]) {
+  for c in range(0u, 4) {
+    let col = [block[4 * c], block[4 * c + 1], block[4 * c + 2], block[4 * c + 3]];
+    for r in range(0u, 4) {
+      let mut byte = 0u8;
+      for i in range(0u, 4) { byte ^= gf_mul(m[(i + 4 - r) % 4], col[i]); }
+      block[4 * c + r] = byte;
+    }
+  }
+}
+
+fn mix_columns(block: &mut [u8]) {
+  mix_columns_with(block, [0x02, 0x03, 0x01, 0x01]);
+}
+
+fn inv_mix_columns(block: &mut [u8]) {
+  mix_columns_with(block, [0x0e, 0x0b, 0x0d, 0x09]);
+}
+
+#[cfg(test)]
+mod test_aes {
+  use super::Aes;
+  use serialize::hex::{ToHex, FromHex};
+
+  // Example vectors from FIPS 197 appendix C
+  fn check_block(key: &str, expected: &str) {
+    let aes = Aes::new(key.from_hex().unwrap()).unwrap();
+    let mut block = "00112233445566778899aabbccddeeff".from_hex().unwrap();
+    aes.encrypt_block(block);
+    assert_eq!(block.to_hex(), expected.to_owned());
+    aes.decrypt_block(block);
+    assert_eq!(block.to_hex(), ~"00112233445566778899aabbccddeeff");
+  }
+
+  #[test]
+  fn test_block() {
+    check_block("000102030405060708090a0b0c0d0e0f", "69c4e0d86a7b0430d8cdb78070b4c55a");
+    check_block("000102030405060708090a0b0c0d0e0f1011121314151617", "dda97ca4864cdfe06eaf70a0ec0d7191");
+    check_block("000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f",
+                "8ea2b7ca516745bfeafc49904b496089");
+    assert!(Aes::new([0u8, ..20]).is_none());
+  }
+
+  #[test]
+  fn test_cbc() {
+    // CBC-AES128 from NIST SP 800-38A F.2.1, followed by a block of padding
+    let aes = Aes::new("2b7e151628aed2a6abf7158809cf4f3c".from_hex().unwrap()).unwrap();
+    let iv = "000102030405060708090a0b0c0d0e0f".from_hex().unwrap();
+    let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51".from_hex().unwrap();
+    let ciphertext = aes.cbc_encrypt(iv, plaintext);
+    assert_eq!(ciphertext.len(), 48);
+    assert_eq!(ciphertext.slice_to(32).to_hex(),
+               ~"7649abac8119b246cee98e9b12e9197d5086cb9b507219ee95db113a917678b2");
+    assert_eq!(aes.cbc_decrypt(iv, ciphertext).unwrap(), plaintext);
+
+    let short = aes.cbc_encrypt(iv, bytes!("hello"));
+    assert_eq!(short.len(), 16);
+    assert_eq!(aes.cbc_decrypt(iv, short).unwrap(), bytes!("hello").to_owned());
+  }
+
+  #[test]
+  fn test_ctr() {
+    // CTR-AES256 from NIST SP 800-38A F.5.5
+    let aes = Aes::new("603deb1015ca71be2b73aef0857d77811f352c073b6108d72d9810a30914dff4".from_hex().unwrap()).unwrap();
+    let iv = "f0f1f2f3f4f5f6f7f8f9fafbfcfdfeff".from_hex().unwrap();
+    let plaintext = "6bc1bee22e409f96e93d7e117393172aae2d8a571e03ac9c9eb76fac45af8e51".from_hex().unwrap();
+    let ciphertext = aes.ctr_xor(iv, plaintext);
+    assert_eq!(ciphertext.to_hex(), ~"601ec313775789a5b7a7f504bbf3d228f443e3ca4d62b59aca84e990cacaf5c5");
+    assert_eq!(aes.ctr_xor(iv, ciphertext), plaintext);
+    // Partial blocks use only as much keystream as they need
+    assert_eq!(aes.ctr_xor(iv, plaintext.slice_to(20)), ciphertext.slice_to(20).to_owned());
+
+    // The counter carries across all 16 bytes
+    let keystream = aes.ctr_xor([0xffu8, ..16], [0u8, ..32]);
+    assert_eq!(keystream.to_hex(), ~"3b3c2921c85a24de9ac606ce6d1d60cce568f68194cf76d6174d4cc04310a854");
+  }
+
+  #[test]
+  fn test_cbc_bad_padding() {
+    let aes = Aes::new([0u8, ..16]).unwrap();
+    let iv = [0u8, ..16];
+    let mut ciphertext = aes.cbc_encrypt(iv, bytes!("hello"));
+    // Flipping a bit of the IV block flips the same bit of the padding
+    let mut iv2 = iv;
+    iv2[15] ^= 1;
+    assert!(aes.cbc_decrypt(iv2, ciphertext).is_none());
+    ciphertext.pop();
+    assert!(aes.cbc_decrypt(iv, ciphertext).is_none());
+  }
+}
@@ -0,0 +1,450 @@
+use std::slice;
+use std::num::{Zero, One};
+use bignum::BigUint;
+use super::{PublicKey, PrivateKey, OtherPrimeInfo, RsaResult, DecodingError, InvalidKey,
+            i2osp, os2ip, modulus_len};
+
+/// Universal tag of an ASN.1 INTEGER
+pub static TAG_INTEGER: u8 = 0x02;
+/// Universal tag of an ASN.1 BIT STRING
+pub static TAG_BIT_STRING: u8 = 0x03;
+/// Universal tag of an ASN.1 OCTET STRING
+pub static TAG_OCTET_STRING: u8 = 0x04;
+/// Universal tag of an ASN.1 NULL
+pub static TAG_NULL: u8 = 0x05;
+/// Universal tag of an ASN.1 OBJECT IDENTIFIER
+pub static TAG_OID: u8 = 0x06;
+/// Universal tag of a constructed ASN.1 SEQUENCE
+pub static TAG_SEQUENCE: u8 = 0x30;
+
+/// DER encoding of a length, in the short form below 128 and the long form otherwise
+fn encode_length(len: uint) -> ~[u8] {
+  if len < 0x80 { return ~[len as u8] }
+
+  let mut count = 0u;
+  let mut rest = len;
+  while rest > 0 { count += 1; rest >>= 8; }
+
+  let mut bytes = slice::with_capacity(count + 1);
+  bytes.push(0x80u8 | (count as u8));
+  for i in range(0, count) {
+    bytes.push((len >> (8 * (count - 1 - i))) as u8);
+  }
+  bytes
+}
+
+/// Tag, length and value of a single DER element
+pub fn encode_tlv(tag: u8, content: &[u8]) -> ~[u8] {
+  let mut out = ~[tag];
+  out.push_all(encode_length(content.len()));
+  out.push_all(content);
+  out
+}
+
+/// DER encoding of a non-negative INTEGER, using the fewest bytes that keep the sign bit clear
+pub fn encode_integer(m: &BigUint) -> ~[u8] {
+  let bytes = i2osp(m, modulus_len(m)).unwrap();
+  let mut content = slice::with_capacity(bytes.len() + 1);
+  if bytes.is_empty() || bytes[0] & 0x80 != 0 { content.push(0x00u8); }
+  content.push_all(bytes);
+  encode_tlv(TAG_INTEGER, content)
+}
+
+/// DER encoding of a BIT STRING holding whole bytes
+pub fn encode_bit_string(content: &[u8]) -> ~[u8] {
+  let mut bits = ~[0x00u8];
+  bits.push_all(content);
+  encode_tlv(TAG_BIT_STRING, bits)
+}
+
+/// DER encoding of a SEQUENCE of already encoded elements
+pub fn encode_sequence(elements: &[~[u8]]) -> ~[u8] {
+  let mut content = ~[];
+  for element in elements.iter() { content.push_all(*element); }
+  encode_tlv(TAG_SEQUENCE, content)
+}
+
+/// Reads DER elements in order from a byte slice
+/// Anything that isn't strict DER is rejected with `DecodingError`
+pub struct DerReader<'a> {
+  priv data: &'a [u8],
+  priv pos: uint
+}
+
+impl<'a> DerReader<'a> {
+  pub fn new(data: &'a [u8]) -> DerReader<'a> {
+    DerReader{ data: data, pos: 0 }
+  }
+
+  /// True once every element has been read
+  pub fn is_empty(&self) -> bool {
+    self.pos == self.data.len()
+  }
+
+  /// Tag of the next element, without reading it
+  pub fn peek_tag(&self) -> Option<u8> {
+    if self.is_empty() { None } else { Some(self.data[self.pos]) }
+  }
+
+  fn read_byte(&mut self) -> RsaResult<u8> {
+    if self.is_empty() { return Err(DecodingError) }
+    let byte = self.data[self.pos];
+    self.pos += 1;
+    Ok(byte)
+  }
+
+  fn read_length(&mut self) -> RsaResult<uint> {
+    let first = try!(self.read_byte());
+    if first < 0x80 { return Ok(first as uint) }
+
+    // Indefinite lengths aren't DER, and nothing we read needs more than 4 length bytes
+    let count = (first & 0x7f) as uint;
+    if count == 0 || count > 4 { return Err(DecodingError) }
+
+    let mut len = 0u;
+    for i in range(0, count) {
+      let byte = try!(self.read_byte());
+      if i == 0 && byte == 0 { return Err(DecodingError) }
+      len = (len << 8) | (byte as uint);
+    }
+    // The long form is only allowed when the short form can't be used
+    if len < 0x80 { return Err(DecodingError) }
+    Ok(len)
+  }
+
+  /// Reads an element with the given tag and returns its content
+  pub fn read_tlv(&mut self, tag: u8) -> RsaResult<&'a [u8]> {
+    if try!(self.read_byte()) != tag { return Err(DecodingError) }
+    let len = try!(self.read_length());
+    if len > self.data.len() - self.pos { return Err(DecodingError) }
+
+    let content = self.data.slice(self.pos, self.pos + len);
+    self.pos += len;
+    Ok(content)
+  }
+
+  /// Reads a SEQUENCE and returns a reader over its elements
+  pub fn read_sequence(&mut self) -> RsaResult<DerReader<'a>> {
+    let content = try!(self.read_tlv(TAG_SEQUENCE));
+    Ok(DerReader::new(content))
+  }
+
+  /// Reads a non-negative INTEGER
+  pub fn read_integer(&mut self) -> RsaResult<BigUint> {
+    let content = try!(self.read_tlv(TAG_INTEGER));
+    if content.is_empty() { return Err(DecodingError) }
+    // Negative numbers never appear in RSA keys
+    if content[0] & 0x80 != 0 { return Err(DecodingError) }
+    // A leading zero byte is only allowed to clear the sign bit
+    if content.len() > 1 && content[0] == 0 && content[1] & 0x80 == 0 { return Err(DecodingError) }
+    Ok(os2ip(content))
+  }
+
+  /// Reads a non-negative INTEGER small enough for a `uint`
+  pub fn read_uint(&mut self) -> RsaResult<uint> {
+    let n = try!(self.read_integer());
+    if n.bits() > 32 { return Err(DecodingError) }
+    let bytes = i2osp(&n, 4).unwrap();
+    Ok(bytes.iter().fold(0u, |acc, &byte| (acc << 8) | (byte as uint)))
+  }
+
+  /// Reads a BIT STRING holding whole bytes
+  pub fn read_bit_string(&mut self) -> RsaResult<&'a [u8]> {
+    let content = try!(self.read_tlv(TAG_BIT_STRING));
+    // The first byte counts the unused bits at the end
+    if content.is_empty() || content[0] != 0 { return Err(DecodingError) }
+    Ok(content.slice_from(1))
+  }
+}
+
+/// Reads a single SEQUENCE spanning all of `der`
+pub fn read_outer_sequence<'a>(der: &'a [u8]) -> RsaResult<DerReader<'a>> {
+  let mut reader = DerReader::new(der);
+  let seq = try!(reader.read_sequence());
+  if !reader.is_empty() { return Err(DecodingError) }
+  Ok(seq)
+}
+
+impl PublicKey {
+  /// DER encoding of the PKCS#1 RSAPublicKey structure
+  pub fn to_pkcs1_der(&self) -> ~[u8] {
+    encode_sequence([encode_integer(&self.n), encode_integer(&self.e)])
+  }
+
+  /// Public key from the DER encoding of a PKCS#1 RSAPublicKey structure
+  pub fn from_pkcs1_der(der: &[u8]) -> RsaResult<PublicKey> {
+    let mut seq = try!(read_outer_sequence(der));
+    let n = try!(seq.read_integer());
+    let e = try!(seq.read_integer());
+    if !seq.is_empty() { return Err(DecodingError) }
+    if n.is_zero() || e.is_zero() { return Err(InvalidKey) }
+    Ok(PublicKey::new(n, e))
+  }
+}
+
+impl PrivateKey {
+  /// DER encoding of the PKCS#1 RSAPrivateKey structure
+  /// Multi-prime keys are version 1, with the extra primes in otherPrimeInfos
+  /// Fails with `InvalidKey` if the key doesn't know its prime factors
+  pub fn to_pkcs1_der(&self) -> RsaResult<~[u8]> {
+    let crt = match self.crt {
+      Some(ref crt) => crt,
+      None          => return Err(InvalidKey)
+    };
+    let multi_prime = !crt.other_primes.is_empty();
+    let version: BigUint = if multi_prime { One::one() } else { Zero::zero() };
+    let mut fields = ~[encode_integer(&version), encode_integer(&self.n),
+                       encode_integer(&self.e), encode_integer(&self.d),
+                       encode_integer(&crt.p), encode_integer(&crt.q),
+                       encode_integer(&crt.dp), encode_integer(&crt.dq),
+                       encode_integer(&crt.qinv)];
+    if multi_prime {
+      let infos: ~[~[u8]] = crt.other_primes.iter().map(|info| {
+        encode_sequence([encode_integer(&info.r), encode_integer(&info.d), encode_integer(&info.t)])
+      }).collect();
+      fields.push(encode_sequence(infos));
+    }
+    Ok(encode_sequence(fields))
+  }
+
+  /// Private key from the DER encoding of a PKCS#1 RSAPrivateKey structure
+  /// Two-prime keys are version 0, and multi-prime keys version 1
+  /// The CRT values must be consistent with the primes and private exponent
+  pub fn from_pkcs1_der(der: &[u8]) -> RsaResult<PrivateKey> {
+    let mut seq = try!(read_outer_sequence(der));
+    let version = try!(seq.read_integer());
+    let one: BigUint = One::one();
+    if !version.is_zero() && version != one { return Err(InvalidKey) }
+
+    let n = try!(seq.read_integer());
+    let e = try!(seq.read_integer());
+    let d = try!(seq.read_integer());
+    let p = try!(seq.read_integer());
+    let q = try!(seq.read_integer());
+    let dp = try!(seq.read_integer());
+    let dq = try!(seq.read_integer());
+    let qinv = try!(seq.read_integer());
+
+    // Each OtherPrimeInfo is the prime, its exponent and its coefficient
+    let mut others = ~[];
+    if version == one {
+      let mut infos = try!(seq.read_sequence());
+      if infos.is_empty() { return Err(DecodingError) }
+      while !infos.is_empty() {
+        let mut info = try!(infos.read_sequence());
+        let r = try!(info.read_integer());
+        let d = try!(info.read_integer());
+        let t = try!(info.read_integer());
+        if !info.is_empty() { return Err(DecodingError) }
+        others.push(OtherPrimeInfo{ r: r, d: d, t: t });
+      }
+    }
+    if !seq.is_empty() { return Err(DecodingError) }
+
+    let mut factors = ~[p, q];
+    for info in others.iter() { factors.push(info.r.clone()); }
+    if factors.iter().fold(one.clone(), |product, r| product * *r) != n { return Err(InvalidKey) }
+    let key = try!(PrivateKey::from_multi_primes(factors, e, d));
+    let consistent = match key.crt {
+      Some(ref crt) => crt.dp == dp && crt.dq == dq && crt.qinv == qinv && crt.other_primes == others,
+      None          => false
+    };
+    if !consistent { return Err(InvalidKey) }
+    Ok(key)
+  }
+}
+
+#[cfg(test)]
+mod test_der {
+  use super::{DerReader, encode_integer, encode_sequence, encode_tlv, encode_bit_string};
+  use super::super::{PublicKey, PrivateKey, DecodingError, InvalidKey};
+  use hash::SHA256;
+  use bignum::ToBigUint;
+  use serialize::hex::{ToHex, FromHex};
+  use std::slice;
+
+  // The same 1024-bit OpenSSL key as the tests in lib.rs, as written by `openssl rsa -outform DER`
+  static OPENSSL_PRIVATE_DER: &'static str =
+    "3082025d02010002818100ae36ca6d0203a4524ee1f7649bd628e19256ae3380\
+     11fa9af93b19b1ccc2b957ef05860c2ad0cb454f9d876d73a8b73d57ae51e0d6\
+     b875d66d268433457da6fa069b9983c4e2e747f5b513b28e09c7f654eb0fa5c0\
+     46804d3f5d1733721e0c730379f1cf57c424740715b781984d286a8724d12f41\
+     747382d11f6e6b309c65bf02030100010281800fb111b495dcdc1d29cbc2abc6\
+     3998ff105e4c5c892995680610e3b4971be6f3dbaca4b14dddf6a12c95a4d01f\
+     fdcf5b701d5c74a3028462a6fc342626193643e2c77938dfe57f3d9ea0eb6ae7\
+     6c1736848ec06eeed11b53e36baef0eb2756c3b573938c04b094784a54b2cae8\
+     79091b1cab3e2f75f152f37beabcbf36749b21024100e7279587a2c3e0a29d5e\
+     0821b3544fd66ad308af27585093a536391ff934b7e06f0adf3ec6f62243f3c8\
+     bd7301ea1c2c504b95b021544a8476053f2b1e051d11024100c0f0700ad46ef2\
+     6358510ef4c7d817b1138ab126f10d51772159073fd55c422ebce236cb5cb1ae\
+     b5dfa6706cba360ce31bba729625f23cfe9413f445b39f95cf024100a1262687\
+     d5c04b05f341cfd776c6eb95e70952bf91656f5f4f497b0fc053b17144d8c684\
+     647ec9aaa7f525a65d0a5233ce92b33b8430d8767a34f431e20edc91024052fc\
+     d864801d3c9ae898ed02f2968b123a629472388acef89bda6c58d38ffc539821\
+     010a95d3435fad542cd5477f0a2a017a3fe13f6f233729402ff86f1b7c570241\
+     00dc7147379dc125d2dae276c111c3d14147ba632fd3d39b19c37d3cd566fce4\
+     fdf454ceafeaee3264435382522d0e3afdce0f31e64f47e673e58fec76b93c37\
+     36";
+  static OPENSSL_PUBLIC_DER: &'static str =
+    "30818902818100ae36ca6d0203a4524ee1f7649bd628e19256ae338011fa9af9\
+     3b19b1ccc2b957ef05860c2ad0cb454f9d876d73a8b73d57ae51e0d6b875d66d\
+     268433457da6fa069b9983c4e2e747f5b513b28e09c7f654eb0fa5c046804d3f\
+     5d1733721e0c730379f1cf57c424740715b781984d286a8724d12f41747382d1\
+     1f6e6b309c65bf0203010001";
+
+  #[test]
+  fn test_encode_integer() {
+    assert_eq!(encode_integer(&0u.to_biguint().unwrap()).to_hex(), ~"020100");
+    assert_eq!(encode_integer(&127u.to_biguint().unwrap()).to_hex(), ~"02017f");
+    assert_eq!(encode_integer(&128u.to_biguint().unwrap()).to_hex(), ~"02020080");
+    assert_eq!(encode_integer(&256u.to_biguint().unwrap()).to_hex(), ~"02020100");
+    assert_eq!(encode_integer(&65537u.to_biguint().unwrap()).to_hex(), ~"0203010001");
+  }
+
+  #[test]
+  fn test_encode_long_length() {
+    let der = encode_tlv(0x04, slice::from_elem(200, 0u8));
+    assert_eq!(der.slice_to(3).to_hex(), ~"0481c8");
+    let der = encode_tlv(0x04, slice::from_elem(300, 0u8));
+    assert_eq!(der.slice_to(4).to_hex(), ~"0482012c");
+    assert_eq!(der.len(), 304);
+  }
+
+  #[test]
+  fn test_bit_string() {
+    let der = encode_bit_string([0xde, 0xad]);
+    assert_eq!(der.to_hex(), ~"030300dead");
+    assert_eq!(DerReader::new(der).read_bit_string().unwrap().to_owned(), ~[0xdeu8, 0xad]);
+    // Trailing unused bits never appear in keys
+    assert_eq!(DerReader::new("030304dead".from_hex().unwrap()).read_bit_string().err(),
+               Some(DecodingError));
+  }
+
+  #[test]
+  fn test_reader_round_trip() {
+    let der = encode_sequence([encode_integer(&0u.to_biguint().unwrap()),
+                               encode_integer(&300u.to_biguint().unwrap())]);
+    let mut reader = DerReader::new(der);
+    let mut seq = reader.read_sequence().unwrap();
+    assert!(reader.is_empty());
+    assert_eq!(seq.read_integer().unwrap(), 0u.to_biguint().unwrap());
+    assert_eq!(seq.read_integer().unwrap(), 300u.to_biguint().unwrap());
+    assert!(seq.is_empty());
+    assert_eq!(seq.read_integer().err(), Some(DecodingError));
+
+    let mut reader = DerReader::new(der);
+    let mut seq = reader.read_sequence().unwrap();
+    assert_eq!(seq.read_uint().unwrap(), 0);
+    assert_eq!(seq.read_uint().unwrap(), 300);
+  }
+
+  #[test]
+  fn test_reader_rejects_non_der() {
+    let read = |hex: &str| DerReader::new(hex.from_hex().unwrap()).read_integer().err();
+    // Negative
+    assert_eq!(read("020180"), Some(DecodingError));
+    // Unnecessary leading zero
+    assert_eq!(read("0202007f"), Some(DecodingError));
+    // Empty content
+    assert_eq!(read("0200"), Some(DecodingError));
+    // Long form length where the short form fits
+    assert_eq!(read("0281017f"), Some(DecodingError));
+    // Indefinite length
+    assert_eq!(read("02807f0000"), Some(DecodingError));
+    // Truncated content
+    assert_eq!(read("0203010001".slice_to(8)), Some(DecodingError));
+    // Wrong tag
+    assert_eq!(read("0401ff"), Some(DecodingError));
+  }
+
+  #[test]
+  fn test_public_key_der() {
+    let der = OPENSSL_PUBLIC_DER.from_hex().unwrap();
+    let public = PublicKey::from_pkcs1_der(der).unwrap();
+    assert_eq!(public.e, 65537u.to_biguint().unwrap());
+    assert_eq!(public.key_size, 1024);
+    assert_eq!(public.to_pkcs1_der(), der);
+  }
+
+  #[test]
+  fn test_private_key_der() {
+    let der = OPENSSL_PRIVATE_DER.from_hex().unwrap();
+    let private = PrivateKey::from_pkcs1_der(der).unwrap();
+    assert_eq!(private.to_pkcs1_der().unwrap(), der);
+
+    let public = private.public_key();
+    assert_eq!(public.to_pkcs1_der(), OPENSSL_PUBLIC_DER.from_hex().unwrap());
+    let m = 42u.to_biguint().unwrap();
+    assert_eq!(private.decrypt_biguint(&public.encrypt_biguint(&m).unwrap()).unwrap(), m);
+  }
+
+  // A three-prime 1024-bit key from `openssl genpkey -pkeyopt rsa_keygen_primes:3`
+  static OPENSSL_MULTI_PRIME_DER: &'static str =
+    "3082027d02010102818100ad200b6ee79aa396c0fdbe0d548a39121511af2137\
+     2e9ff2b085b0bbc9134b0d085a7d2132eabf79a19fbd34737e3381694960fce4\
+     7c9b13690df16d0054a5f5106665f389241546dcf281f76cbe564a1ee340af2c\
+     0cc84866dd6a8c780e9ea87c8fd28498f353fb74a4adf8c9b14a7ecf384e9fc5\
+     0ddc5d3af8c0ea4878376302030100010281800cbef4388912a8829bd8d29a36\
+     a42a897b459f082a00e3c3c15d4662bcee0fb378eef8916e273c4b9f2b9b5f73\
+     5dbbf3dd537bc20fadbb0fd9842a8664f4b675120269e4a14f1ab3d595850cea\
+     d8ce1dd51ae1f70305c6f3bd85f5729531419ee26920dd7524fbdef4db70f420\
+     b107aef11e4110a9fb8d559f543ebac7a60109022b3f2529ed2e7cf703a3019c\
+     3570ac39b8607df5c682668e6618a7796f6d987827bbd30704872cc786de2c5b\
+     022b1a4dc057caf76400210ace57adfd6afae2aae2c4537b75ba2a613f06728f\
+     55e13c1520a78891380d062927022b124b471153359b2eef8be8e0c6aaeef20e\
+     ea8f491ac071a2fca390980acd708a3a6d520c2ac97b6904771f022b01128a75\
+     09e16c4255168bb32774dd97bd061a98c68e920e350c21f5b15606ba5828c45c\
+     10455b73ac7ca1022b2b5965c99d4ea503128eaaeafdefef8ce9a9f95b0afe6c\
+     5a7a2de35499f32b6999011c6213e8ec31089c9930818a308187022b1aaefa6d\
+     6458e809c458bbf61758c96920cdb50ca61545b6624f0ad888729c61fd6e4a74\
+     2afa5ef45dd93f022b0097728bec56a0465f290c79c748bba66e08c183d746cf\
+     75dcb61dea7114016d96bdd43ec1192df3c304e7022b012a90bfe33aad84d8e9\
+     ed2a65a29b777e11905311a429ca431b680e3d2ef472e7a44c71a396ec881584\
+     e4";
+
+  // `openssl dgst -sha256 -sign` of "multi-prime message" with that key
+  static OPENSSL_MULTI_PRIME_SIGNATURE: &'static str =
+    "90d23a5a13f9ff864ad7f2766f00c3202899affa448b07a91f72ba601cceb1ab\
+     f486b99244e5a9086d4fdfc658f0c1ae0fcb8edef67b2752f36108d389fdfd3e\
+     34459a3f15df8ebdb14f8025540182e4823554e2141489a42994028d36ca8759\
+     b4c9dd07f9daf1d8acf52487e3286f7e43d53911b83cad06a2686525063246a5";
+
+  #[test]
+  fn test_multi_prime_key_der() {
+    let der = OPENSSL_MULTI_PRIME_DER.from_hex().unwrap();
+    let private = PrivateKey::from_pkcs1_der(der).unwrap();
+    assert_eq!(private.crt.get_ref().other_primes.len(), 1);
+    assert_eq!(private.to_pkcs1_der().unwrap(), der);
+
+    // Signing decrypts with all three primes
+    assert_eq!(private.sign(SHA256, bytes!("multi-prime message")).unwrap(),
+               OPENSSL_MULTI_PRIME_SIGNATURE.from_hex().unwrap());
+
+    // Version 1 keys need their other primes, and no other versions are known
+    let mut two_primes = der.slice_to(500).to_owned();
+    two_primes[2] = 0x01;
+    two_primes[3] = 0xf0;
+    assert_eq!(PrivateKey::from_pkcs1_der(two_primes).err(), Some(DecodingError));
+    let mut version_two = der.clone();
+    version_two[6] = 0x02;
+    assert_eq!(PrivateKey::from_pkcs1_der(version_two).err(), Some(InvalidKey));
+  }
+
+  #[test]
+  fn test_private_key_der_rejects_bad_keys() {
+    let der = OPENSSL_PRIVATE_DER.from_hex().unwrap();
+    // Trailing garbage after the structure
+    let mut trailing = der.clone();
+    trailing.push(0x00);
+    assert_eq!(PrivateKey::from_pkcs1_der(trailing).err(), Some(DecodingError));
+    // Flipping the last bit of qInv leaves a well-formed but inconsistent key
+    let mut inconsistent = der.clone();
+    let last = inconsistent.len() - 1;
+    inconsistent[last] ^= 1;
+    assert_eq!(PrivateKey::from_pkcs1_der(inconsistent).err(), Some(InvalidKey));
+    // Keys without their prime factors have no PKCS#1 encoding
+    let private = PrivateKey::from_pkcs1_der(der).unwrap();
+    let non_crt = PrivateKey::new(private.n.clone(), private.e.clone(), private.d.clone());
+    assert_eq!(non_crt.to_pkcs1_der().err(), Some(InvalidKey));
+  }
+}
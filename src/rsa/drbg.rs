@@ -0,0 +1,145 @@
+use std::slice;
+use rand::Rng;
+use hash::{Digest, HashT};
+use hmac::{Hmac, hmac};
+
+/// Most bytes a single `generate` call may return
+pub static MAX_REQUEST_BYTES: uint = 1 << 16;
+/// Number of `generate` calls allowed before the generator must be reseeded
+pub static RESEED_INTERVAL: u64 = 1 << 48;
+
+/// HMAC_DRBG from NIST SP 800-90A, a deterministic random bit generator
+/// The same seed always gives the same output, so seeding it with a fixed value
+/// makes key generation reproducible. Implements `Rng`
+pub struct HmacDrbg {
+  priv hash: HashT,
+  priv k: ~[u8],
+  priv v: ~[u8],
+  priv reseed_counter: u64
+}
+
+impl HmacDrbg {
+  /// Instantiate from `entropy`, `nonce` and an optional personalization string
+  /// The entropy must hold at least as many bits as the security strength wanted
+  pub fn new(hash: HashT, entropy: &[u8], nonce: &[u8], personalization: &[u8]) -> HmacDrbg {
+    let len = hash.output_bytes();
+    let mut drbg = HmacDrbg{ hash: hash, k: slice::from_elem(len, 0u8), v: slice::from_elem(len, 1u8),
+                             reseed_counter: 1 };
+    drbg.update([entropy, nonce, personalization]);
+    drbg
+  }
+
+  /// Mix fresh `entropy` and optional `additional` input into the state
+  pub fn reseed(&mut self, entropy: &[u8], additional: &[u8]) {
+    self.update([entropy, additional]);
+    self.reseed_counter = 1;
+  }
+
+  /// Fill `out` with pseudorandom bytes, after mixing in optional `additional` input
+  /// Fails if `out` is longer than `MAX_REQUEST_BYTES` or a reseed is due
+  pub fn generate(&mut self, out: &mut [u8], additional: &[u8]) {
+    assert!(out.len() <= MAX_REQUEST_BYTES);
+    if self.reseed_counter > RESEED_INTERVAL { fail!("HMAC_DRBG must be reseeded"); }
+
+    if !additional.is_empty() { self.update([additional]); }
+    let mut filled = 0;
+    while filled < out.len() {
+      let v = hmac(self.hash, self.k, self.v);
+      self.v = v;
+      for (dst, &src) in out.mut_slice_from(filled).mut_iter().zip(self.v.iter()) { *dst = src; }
+      filled += self.v.len();
+    }
+    self.update([additional]);
+    self.reseed_counter += 1;
+  }
+
+  // The HMAC_DRBG update function, with the provided data as the concatenation of `provided`
+  fn update(&mut self, provided: &[&[u8]]) {
+    for &round in [0u8, 1].iter() {
+      let mut mac = Hmac::new(self.hash, self.k);
+      mac.input(self.v);
+      mac.input([round]);
+      for data in provided.iter() { mac.input(*data); }
+      self.k = mac.result();
+      let v = hmac(self.hash, self.k, self.v);
+      self.v = v;
+
+      // The second round only happens when there is provided data
+      if provided.iter().all(|data| data.is_empty()) { break }
+    }
+  }
+}
+
+impl Rng for HmacDrbg {
+  // Big-endian, so the words are the bytes `generate` would return
+  fn next_u32(&mut self) -> u32 {
+    let mut bytes = [0u8, ..4];
+    self.fill_bytes(bytes);
+    bytes.iter().fold(0u32, |acc, &byte| (acc << 8) | (byte as u32))
+  }
+
+  fn fill_bytes(&mut self, dest: &mut [u8]) {
+    for chunk in dest.mut_chunks(MAX_REQUEST_BYTES) { self.generate(chunk, []); }
+  }
+}
+
+#[cfg(test)]
+mod test_drbg {
+  use super::HmacDrbg;
+  use hash::{SHA256, SHA512};
+  use rand::Rng;
+  use serialize::hex::{FromHex, ToHex};
+  use std::slice;
+
+  // First SHA-256 case from the NIST CAVP HMAC_DRBG vectors, without prediction
+  // resistance: the output of the second 1024-bit request is checked
+  #[test]
+  fn test_cavp_sha256() {
+    let entropy = "ca851911349384bffe89de1cbdc46e6831e44d34a4fb935ee285dd14b71a7488".from_hex().unwrap();
+    let nonce = "659ba96c601dc69fc902940805ec0ca8".from_hex().unwrap();
+    let mut drbg = HmacDrbg::new(SHA256, entropy, nonce, []);
+    let mut out = [0u8, ..128];
+    drbg.generate(out, []);
+    drbg.generate(out, []);
+    assert_eq!(out.to_hex(),
+               ~"e528e9abf2dece54d47c7e75e5fe302149f817ea9fb4bee6f4199697d04d5b89\
+                 d54fbb978a15b5c443c9ec21036d2460b6f73ebad0dc2aba6e624abf07745bc1\
+                 07694bb7547bb0995f70de25d6b29e2d3011bb19d27676c07162c8b5ccde0668\
+                 961df86803482cb37ed6d5c0bb8d50cf1f50d476aa0458bdaba806f48be9dcb8");
+  }
+
+  // Checked against a Python implementation that passes the CAVP vectors
+  #[test]
+  fn test_reseed_and_additional_input() {
+    let entropy = slice::from_fn(32, |i| i as u8);
+    let nonce = slice::from_fn(8, |i| (i + 32) as u8);
+    let mut drbg = HmacDrbg::new(SHA256, entropy, nonce, bytes!("personalization"));
+    let mut out = [0u8, ..32];
+    drbg.generate(out, bytes!("additional"));
+    assert_eq!(out.to_hex(), ~"0da95736aec782c80db300d75f88fd1e833a31d9c4c169e717ca4ca23ad325d6");
+
+    drbg.reseed(slice::from_fn(32, |i| (i + 0x80) as u8), bytes!("reseed"));
+    drbg.generate(out, []);
+    assert_eq!(out.to_hex(), ~"f7d1a842e952c0dbfde72e737a272b0ac40485db4d62202d8d0688cc35a8d7bc");
+  }
+
+  #[test]
+  fn test_sha512_partial_block() {
+    let mut drbg = HmacDrbg::new(SHA512, slice::from_fn(64, |i| i as u8), [], []);
+    let mut out = [0u8, ..80];
+    drbg.generate(out, []);
+    assert_eq!(out.to_hex(),
+               ~"7e5e88a68be34d230a4bb358d0de1be79d9d8ebea72370e2b63db336adeeed0a\
+                 5f3bffc80c9fcbe1bb4987b7b38a2744544967edffab8313a5f17bde1d2bc204\
+                 5884aa5a180ba29abeb665f0118605bf");
+  }
+
+  #[test]
+  fn test_rng() {
+    let entropy = slice::from_fn(32, |i| i as u8);
+    let nonce = slice::from_fn(8, |i| (i + 32) as u8);
+    let mut drbg = HmacDrbg::new(SHA256, entropy, nonce, bytes!("personalization"));
+    assert_eq!(drbg.next_u32(), 0xc080e314);
+    assert_eq!(drbg.next_u32(), 0x32261f33);
+  }
+}
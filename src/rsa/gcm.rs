@@ -0,0 +1,185 @@
+use std::slice;
+use aes::{Aes, BLOCK_LEN};
+
+/// Length of the authentication tag in bytes
+pub static TAG_LEN: uint = 16;
+/// Length of the IV in bytes, the only one supported
+pub static IV_LEN: uint = 12;
+
+/// AES in Galois/Counter Mode from NIST SP 800-38D, with 96-bit IVs and 128-bit tags
+pub struct Gcm {
+  priv aes: Aes,
+  // The hash subkey, the encryption of the zero block
+  priv h: (u64, u64)
+}
+
+// A block as a pair of big-endian 64-bit halves
+fn to_words(block: &[u8]) -> (u64, u64) {
+  let word = |bytes: &[u8]| bytes.iter().fold(0u64, |acc, &byte| (acc << 8) | (byte as u64));
+  (word(block.slice_to(8)), word(block.slice(8, 16)))
+}
+
+fn from_words(x: (u64, u64)) -> ~[u8] {
+  let (hi, lo) = x;
+  let mut out = slice::with_capacity(BLOCK_LEN);
+  for &word in [hi, lo].iter() {
+    for i in range(0u, 8) { out.push((word >> (56 - 8 * i)) as u8); }
+  }
+  out
+}
+
+// Multiplication in GF(2^128) with GCM's reflected bit order, one bit at a time
+// The operands involve the hash subkey, so bits select through masks rather
+// than branches to keep the timing independent of them
+fn gf_mul(x: (u64, u64), y: (u64, u64)) -> (u64, u64) {
+  let (x_hi, x_lo) = x;
+  let (mut v_hi, mut v_lo) = y;
+  let mut z_hi = 0u64;
+  let mut z_lo = 0u64;
+  for i in range(0u, 128) {
+    let bit = if i < 64 { (x_hi >> (63 - i)) & 1 } else { (x_lo >> (127 - i)) & 1 };
+    let mask = 0 - bit;
+    z_hi ^= v_hi & mask;
+    z_lo ^= v_lo & mask;
+    let carry = 0 - (v_lo & 1);
+    v_lo = (v_lo >> 1) | (v_hi << 63);
+    v_hi = (v_hi >> 1) ^ (0xe100000000000000 & carry);
+  }
+  (z_hi, z_lo)
+}
+
+// Increments the last 32 bits of a counter block, wrapping around
+fn inc32(counter: &mut [u8]) {
+  let mut i = BLOCK_LEN;
+  while i > BLOCK_LEN - 4 {
+    i -= 1;
+    counter[i] += 1;
+    if counter[i] != 0 { break }
+  }
+}
+
+impl Gcm {
+  /// GCM with an AES key, or `None` if the key isn't 16, 24 or 32 bytes
+  pub fn new(key: &[u8]) -> Option<Gcm> {
+    let aes = match Aes::new(key) {
+      Some(aes) => aes,
+      None      => return None
+    };
+    let mut h = [0u8, ..16];
+    aes.encrypt_block(h);
+    Some(Gcm{ aes: aes, h: to_words(h) })
+  }
+
+  fn ghash(&self, aad: &[u8], ciphertext: &[u8]) -> (u64, u64) {
+    let mut y = (0u64, 0u64);
+    for data in [aad, ciphertext].iter() {
+      for chunk in data.chunks(BLOCK_LEN) {
+        let mut block = [0u8, ..16];
+        for (dest, &byte) in block.mut_iter().zip(chunk.iter()) { *dest = byte; }
+        let (x_hi, x_lo) = to_words(block);
+        let (y_hi, y_lo) = y;
+        y = gf_mul((y_hi ^ x_hi, y_lo ^ x_lo), self.h);
+      }
+    }
+    let (y_hi, y_lo) = y;
+    gf_mul((y_hi ^ (8 * aad.len() as u64), y_lo ^ (8 * ciphertext.len() as u64)), self.h)
+  }
+
+  // CTR mode starting from the counter after `j0`
+  fn ctr(&self, j0: &[u8], data: &[u8]) -> ~[u8] {
+    let mut counter = j0.to_owned();
+    let mut out = slice::with_capacity(data.len());
+    for chunk in data.chunks(BLOCK_LEN) {
+      inc32(counter);
+      let mut keystream = counter.clone();
+      self.aes.encrypt_block(keystream);
+      for (&byte, &k) in chunk.iter().zip(keystream.iter()) { out.push(byte ^ k); }
+    }
+    out
+  }
+
+  fn tag(&self, j0: &[u8], aad: &[u8], ciphertext: &[u8]) -> ~[u8] {
+    let mut tag = j0.to_owned();
+    self.aes.encrypt_block(tag);
+    for (byte, &s) in tag.mut_iter().zip(from_words(self.ghash(aad, ciphertext)).iter()) { *byte ^= s; }
+    tag
+  }
+
+  fn j0(iv: &[u8]) -> ~[u8] {
+    assert_eq!(iv.len(), IV_LEN);
+    let mut j0 = iv.to_owned();
+    j0.push_all([0u8, 0, 0, 1]);
+    j0
+  }
+
+  /// Encrypt `data`, authenticating it along with `aad`
+  /// Returns the ciphertext, which is as long as `data`, and the tag
+  /// `iv` must be 12 bytes long and never reused with the same key
+  pub fn encrypt(&self, iv: &[u8], aad: &[u8], data: &[u8]) -> (~[u8], ~[u8]) {
+    let j0 = Gcm::j0(iv);
+    let ciphertext = self.ctr(j0, data);
+    let tag = self.tag(j0, aad, ciphertext);
+    (ciphertext, tag)
+  }
+
+  /// Decrypt `data`, or `None` if it or `aad` don't match `tag`
+  pub fn decrypt(&self, iv: &[u8], aad: &[u8], data: &[u8], tag: &[u8]) -> Option<~[u8]> {
+    let j0 = Gcm::j0(iv);
+    let expected = self.tag(j0, aad, data);
+    // Compare every byte so the time taken doesn't depend on where they differ
+    if tag.len() != TAG_LEN { return None }
+    let diff = expected.iter().zip(tag.iter()).fold(0u8, |acc, (&a, &b)| acc | (a ^ b));
+    if diff != 0 { return None }
+    Some(self.ctr(j0, data))
+  }
+}
+
+#[cfg(test)]
+mod test_gcm {
+  use super::Gcm;
+  use serialize::hex::{ToHex, FromHex};
+
+  fn check(key: &str, iv: &str, aad: &str, plaintext: &str, ciphertext: &str, tag: &str) {
+    let gcm = Gcm::new(key.from_hex().unwrap()).unwrap();
+    let iv = iv.from_hex().unwrap();
+    let aad = aad.from_hex().unwrap();
+    let plaintext = plaintext.from_hex().unwrap();
+    let (c, t) = gcm.encrypt(iv, aad, plaintext);
+    assert_eq!(c.to_hex(), ciphertext.to_owned());
+    assert_eq!(t.to_hex(), tag.to_owned());
+    assert_eq!(gcm.decrypt(iv, aad, c, t).unwrap(), plaintext);
+  }
+
+  // Test cases 2, 4 and 16 from the GCM specification
+  #[test]
+  fn test_vectors() {
+    check("00000000000000000000000000000000", "000000000000000000000000", "",
+          "00000000000000000000000000000000", "0388dace60b6a392f328c2b971b2fe78",
+          "ab6e47d42cec13bdf53a67b21257bddf");
+    check("feffe9928665731c6d6a8f9467308308", "cafebabefacedbaddecaf888",
+          "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+          "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525\
+           b16aedf5aa0de657ba637b39",
+          "42831ec2217774244b7221b784d0d49ce3aa212f2c02a4e035c17e2329aca12e21d514b25466931c7d8f6a5aac84aa05\
+           1ba30b396a0aac973d58e091",
+          "5bc94fbc3221a5db94fae95ae7121a47");
+    check("feffe9928665731c6d6a8f9467308308feffe9928665731c6d6a8f9467308308", "cafebabefacedbaddecaf888",
+          "feedfacedeadbeeffeedfacedeadbeefabaddad2",
+          "d9313225f88406e5a55909c5aff5269a86a7a9531534f7da2e4c303d8a318a721c3c0c95956809532fcf0e2449a6b525\
+           b16aedf5aa0de657ba637b39",
+          "522dc1f099567d07f47f37a32a84427d643a8cdcbfe5c0c97598a2bd2555d1aa8cb08e48590dbb3da7b08b1056828838\
+           c5f61e6393ba7a0abcc9f662",
+          "76fc6ece0f4e1768cddf8853bb2d551b");
+  }
+
+  #[test]
+  fn test_tampering() {
+    let gcm = Gcm::new([0x42u8, ..16]).unwrap();
+    let iv = [7u8, ..12];
+    let (mut ciphertext, tag) = gcm.encrypt(iv, bytes!("header"), bytes!("attack at dawn"));
+    assert!(gcm.decrypt(iv, bytes!("header"), ciphertext, tag.slice_to(12)).is_none());
+    assert!(gcm.decrypt(iv, bytes!("other"), ciphertext, tag).is_none());
+    ciphertext[0] ^= 1;
+    assert!(gcm.decrypt(iv, bytes!("header"), ciphertext, tag).is_none());
+  }
+}
@@ -0,0 +1,614 @@
+use std::cmp::min;
+use std::slice;
+
+/// A cryptographic hash function that can be fed input incrementally
+pub trait Digest {
+  /// Feed more input into the hash
+  fn input(&mut self, data: &[u8]);
+
+  /// Finish hashing and return the digest, resetting the hash for reuse
+  fn result(&mut self) -> ~[u8];
+
+  /// Discard all input and start over
+  fn reset(&mut self);
+
+  /// Size of the digest in bytes
+  fn output_bytes(&self) -> uint;
+
+  /// Size of the internal block in bytes
+  fn block_bytes(&self) -> uint;
+}
+
+/// Hash functions supported by the padding and signature schemes
+#[deriving(Eq, Clone, Show)]
+#[allow(non_camel_case_types)]
+pub enum HashT {
+  SHA1,
+  SHA224,
+  SHA256,
+  SHA384,
+  SHA512,
+  /// SHA-512 with its own initial state, truncated to 256 bits
+  SHA512_256
+}
+
+/// Every supported hash function
+pub static ALL_HASHES: [HashT, ..6] = [SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256];
+
+impl HashT {
+  /// A fresh instance of this hash function
+  pub fn digest(&self) -> ~Digest {
+    match *self {
+      SHA1       => ~Sha1::new() as ~Digest,
+      SHA224     => ~Sha224::new() as ~Digest,
+      SHA256     => ~Sha256::new() as ~Digest,
+      SHA384     => ~Sha384::new() as ~Digest,
+      SHA512     => ~Sha512::new() as ~Digest,
+      SHA512_256 => ~Sha512Trunc256::new() as ~Digest
+    }
+  }
+
+  /// Size of this hash function's digest in bytes
+  pub fn output_bytes(&self) -> uint {
+    match *self {
+      SHA1       => 20,
+      SHA224     => 28,
+      SHA256     => 32,
+      SHA384     => 48,
+      SHA512     => 64,
+      SHA512_256 => 32
+    }
+  }
+
+  /// Hash `data` in one go
+  pub fn hash(&self, data: &[u8]) -> ~[u8] {
+    let mut digest = self.digest();
+    digest.input(data);
+    digest.result()
+  }
+}
+
+// Buffers input until there is a full block to hand to a compression function
+struct BlockBuffer {
+  priv pending: ~[u8],
+  priv block_len: uint,
+  priv total_len: u64
+}
+
+impl BlockBuffer {
+  fn new(block_len: uint) -> BlockBuffer {
+    BlockBuffer{ pending: slice::with_capacity(block_len), block_len: block_len, total_len: 0 }
+  }
+
+  fn input(&mut self, data: &[u8], compress: |&[u8]|) {
+    self.total_len += data.len() as u64;
+    let mut data = data;
+
+    // Top up a partially filled block first
+    if !self.pending.is_empty() {
+      let take = min(self.block_len - self.pending.len(), data.len());
+      self.pending.push_all(data.slice_to(take));
+      data = data.slice_from(take);
+      if self.pending.len() < self.block_len { return }
+      compress(self.pending.as_slice());
+      self.pending.truncate(0);
+    }
+
+    while data.len() >= self.block_len {
+      compress(data.slice_to(self.block_len));
+      data = data.slice_from(self.block_len);
+    }
+    self.pending.push_all(data);
+  }
+
+  // Merkle-Damgard padding: a single 1 bit, zeros, then the message length
+  // in bits as a big-endian integer of `length_len` bytes
+  fn finish(&mut self, length_len: uint, compress: |&[u8]|) {
+    let bit_len = self.total_len * 8;
+    let mut tail = self.pending.clone();
+    tail.push(0x80u8);
+    while (tail.len() + length_len) % self.block_len != 0 { tail.push(0u8); }
+    for _ in range(0, length_len - 8) { tail.push(0u8); }
+    for i in range(0u, 8) { tail.push((bit_len >> (56 - 8 * i)) as u8); }
+
+    for block in tail.chunks(self.block_len) { compress(block); }
+    self.reset();
+  }
+
+  fn reset(&mut self) {
+    self.pending.truncate(0);
+    self.total_len = 0;
+  }
+}
+
+fn read_u32_be(bytes: &[u8]) -> u32 {
+  ((bytes[0] as u32) << 24) | ((bytes[1] as u32) << 16) | ((bytes[2] as u32) << 8) | (bytes[3] as u32)
+}
+
+fn read_u64_be(bytes: &[u8]) -> u64 {
+  ((read_u32_be(bytes) as u64) << 32) | (read_u32_be(bytes.slice_from(4)) as u64)
+}
+
+fn write_u32_be(out: &mut ~[u8], x: u32) {
+  for i in range(0u, 4) { out.push((x >> (24 - 8 * i)) as u8); }
+}
+
+fn write_u64_be(out: &mut ~[u8], x: u64) {
+  for i in range(0u, 8) { out.push((x >> (56 - 8 * i)) as u8); }
+}
+
+fn rotl32(x: u32, n: uint) -> u32 { (x << n) | (x >> (32 - n)) }
+fn rotr32(x: u32, n: uint) -> u32 { (x >> n) | (x << (32 - n)) }
+fn rotr64(x: u64, n: uint) -> u64 { (x >> n) | (x << (64 - n)) }
+
+/// SHA-1, as specified in FIPS 180-4
+pub struct Sha1 {
+  priv state: [u32, ..5],
+  priv buffer: BlockBuffer
+}
+
+static SHA1_INIT: [u32, ..5] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0];
+
+impl Sha1 {
+  pub fn new() -> Sha1 {
+    Sha1{ state: SHA1_INIT, buffer: BlockBuffer::new(64) }
+  }
+}
+
+fn sha1_compress(state: &mut [u32, ..5], block: &[u8]) {
+  let mut w = [0u32, ..80];
+  for t in range(0u, 16) { w[t] = read_u32_be(block.slice_from(4 * t)); }
+  for t in range(16u, 80) { w[t] = rotl32(w[t-3] ^ w[t-8] ^ w[t-14] ^ w[t-16], 1); }
+
+  let (mut a, mut b, mut c, mut d, mut e) = (state[0], state[1], state[2], state[3], state[4]);
+  for t in range(0u, 80) {
+    let (f, k) = match t / 20 {
+      0 => ((b & c) | (!b & d),          0x5a827999u32),
+      1 => (b ^ c ^ d,                   0x6ed9eba1u32),
+      2 => ((b & c) | (b & d) | (c & d), 0x8f1bbcdcu32),
+      _ => (b ^ c ^ d,                   0xca62c1d6u32)
+    };
+    let temp = rotl32(a, 5) + f + e + k + w[t];
+    e = d;
+    d = c;
+    c = rotl32(b, 30);
+    b = a;
+    a = temp;
+  }
+
+  state[0] += a;
+  state[1] += b;
+  state[2] += c;
+  state[3] += d;
+  state[4] += e;
+}
+
+impl Digest for Sha1 {
+  fn input(&mut self, data: &[u8]) {
+    let state = &mut self.state;
+    self.buffer.input(data, |block| sha1_compress(state, block));
+  }
+
+  fn result(&mut self) -> ~[u8] {
+    {
+      let state = &mut self.state;
+      self.buffer.finish(8, |block| sha1_compress(state, block));
+    }
+    let mut out = slice::with_capacity(20);
+    for &word in self.state.iter() { write_u32_be(&mut out, word); }
+    self.state = SHA1_INIT;
+    out
+  }
+
+  fn reset(&mut self) {
+    self.state = SHA1_INIT;
+    self.buffer.reset();
+  }
+
+  fn output_bytes(&self) -> uint { 20 }
+  fn block_bytes(&self) -> uint { 64 }
+}
+
+static SHA256_K: [u32, ..64] = [
+  0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+  0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+  0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+  0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+  0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+  0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+  0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+  0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2
+];
+
+// Shared implementation of the SHA-256 family, which differs only in its
+// initial state and how much of the final state is output
+struct Engine256 {
+  priv state: [u32, ..8],
+  priv init: [u32, ..8],
+  priv buffer: BlockBuffer
+}
+
+impl Engine256 {
+  fn new(init: [u32, ..8]) -> Engine256 {
+    Engine256{ state: init, init: init, buffer: BlockBuffer::new(64) }
+  }
+
+  fn input(&mut self, data: &[u8]) {
+    let state = &mut self.state;
+    self.buffer.input(data, |block| sha256_compress(state, block));
+  }
+
+  fn result(&mut self, output_len: uint) -> ~[u8] {
+    {
+      let state = &mut self.state;
+      self.buffer.finish(8, |block| sha256_compress(state, block));
+    }
+    let mut out = slice::with_capacity(32);
+    for &word in self.state.iter() { write_u32_be(&mut out, word); }
+    out.truncate(output_len);
+    self.state = self.init;
+    out
+  }
+
+  fn reset(&mut self) {
+    self.state = self.init;
+    self.buffer.reset();
+  }
+}
+
+fn sha256_compress(state: &mut [u32, ..8], block: &[u8]) {
+  let mut w = [0u32, ..64];
+  for t in range(0u, 16) { w[t] = read_u32_be(block.slice_from(4 * t)); }
+  for t in range(16u, 64) {
+    let s0 = rotr32(w[t-15], 7) ^ rotr32(w[t-15], 18) ^ (w[t-15] >> 3);
+    let s1 = rotr32(w[t-2], 17) ^ rotr32(w[t-2], 19) ^ (w[t-2] >> 10);
+    w[t] = w[t-16] + s0 + w[t-7] + s1;
+  }
+
+  let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+  let (mut e, mut f, mut g, mut h) = (state[4], state[5], state[6], state[7]);
+  for t in range(0u, 64) {
+    let sum1 = rotr32(e, 6) ^ rotr32(e, 11) ^ rotr32(e, 25);
+    let ch = (e & f) ^ (!e & g);
+    let temp1 = h + sum1 + ch + SHA256_K[t] + w[t];
+    let sum0 = rotr32(a, 2) ^ rotr32(a, 13) ^ rotr32(a, 22);
+    let maj = (a & b) ^ (a & c) ^ (b & c);
+    let temp2 = sum0 + maj;
+    h = g;
+    g = f;
+    f = e;
+    e = d + temp1;
+    d = c;
+    c = b;
+    b = a;
+    a = temp1 + temp2;
+  }
+
+  state[0] += a;
+  state[1] += b;
+  state[2] += c;
+  state[3] += d;
+  state[4] += e;
+  state[5] += f;
+  state[6] += g;
+  state[7] += h;
+}
+
+/// SHA-256, as specified in FIPS 180-4
+pub struct Sha256 {
+  priv engine: Engine256
+}
+
+static SHA256_INIT: [u32, ..8] = [
+  0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a,
+  0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19
+];
+
+impl Sha256 {
+  pub fn new() -> Sha256 {
+    Sha256{ engine: Engine256::new(SHA256_INIT) }
+  }
+}
+
+impl Digest for Sha256 {
+  fn input(&mut self, data: &[u8]) { self.engine.input(data) }
+  fn result(&mut self) -> ~[u8] { self.engine.result(32) }
+  fn reset(&mut self) { self.engine.reset() }
+  fn output_bytes(&self) -> uint { 32 }
+  fn block_bytes(&self) -> uint { 64 }
+}
+
+/// SHA-224, as specified in FIPS 180-4
+pub struct Sha224 {
+  priv engine: Engine256
+}
+
+static SHA224_INIT: [u32, ..8] = [
+  0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939,
+  0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4
+];
+
+impl Sha224 {
+  pub fn new() -> Sha224 {
+    Sha224{ engine: Engine256::new(SHA224_INIT) }
+  }
+}
+
+impl Digest for Sha224 {
+  fn input(&mut self, data: &[u8]) { self.engine.input(data) }
+  fn result(&mut self) -> ~[u8] { self.engine.result(28) }
+  fn reset(&mut self) { self.engine.reset() }
+  fn output_bytes(&self) -> uint { 28 }
+  fn block_bytes(&self) -> uint { 64 }
+}
+
+static SHA512_K: [u64, ..80] = [
+  0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+  0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+  0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+  0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+  0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+  0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+  0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+  0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+  0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+  0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+  0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+  0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+  0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+  0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+  0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+  0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+  0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+  0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+  0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+  0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817
+];
+
+// Shared implementation of the SHA-512 family, which differs only in its
+// initial state and how much of the final state is output
+struct Engine512 {
+  priv state: [u64, ..8],
+  priv init: [u64, ..8],
+  priv buffer: BlockBuffer
+}
+
+impl Engine512 {
+  fn new(init: [u64, ..8]) -> Engine512 {
+    Engine512{ state: init, init: init, buffer: BlockBuffer::new(128) }
+  }
+
+  fn input(&mut self, data: &[u8]) {
+    let state = &mut self.state;
+    self.buffer.input(data, |block| sha512_compress(state, block));
+  }
+
+  fn result(&mut self, output_len: uint) -> ~[u8] {
+    {
+      let state = &mut self.state;
+      self.buffer.finish(16, |block| sha512_compress(state, block));
+    }
+    let mut out = slice::with_capacity(64);
+    for &word in self.state.iter() { write_u64_be(&mut out, word); }
+    out.truncate(output_len);
+    self.state = self.init;
+    out
+  }
+
+  fn reset(&mut self) {
+    self.state = self.init;
+    self.buffer.reset();
+  }
+}
+
+fn sha512_compress(state: &mut [u64, ..8], block: &[u8]) {
+  let mut w = [0u64, ..80];
+  for t in range(0u, 16) { w[t] = read_u64_be(block.slice_from(8 * t)); }
+  for t in range(16u, 80) {
+    let s0 = rotr64(w[t-15], 1) ^ rotr64(w[t-15], 8) ^ (w[t-15] >> 7);
+    let s1 = rotr64(w[t-2], 19) ^ rotr64(w[t-2], 61) ^ (w[t-2] >> 6);
+    w[t] = w[t-16] + s0 + w[t-7] + s1;
+  }
+
+  let (mut a, mut b, mut c, mut d) = (state[0], state[1], state[2], state[3]);
+  let (mut e, mut f, mut g, mut h) = (state[4], state[5], state[6], state[7]);
+  for t in range(0u, 80) {
+    let sum1 = rotr64(e, 14) ^ rotr64(e, 18) ^ rotr64(e, 41);
+    let ch = (e & f) ^ (!e & g);
+    let temp1 = h + sum1 + ch + SHA512_K[t] + w[t];
+    let sum0 = rotr64(a, 28) ^ rotr64(a, 34) ^ rotr64(a, 39);
+    let maj = (a & b) ^ (a & c) ^ (b & c);
+    let temp2 = sum0 + maj;
+    h = g;
+    g = f;
+    f = e;
+    e = d + temp1;
+    d = c;
+    c = b;
+    b = a;
+    a = temp1 + temp2;
+  }
+
+  state[0] += a;
+  state[1] += b;
+  state[2] += c;
+  state[3] += d;
+  state[4] += e;
+  state[5] += f;
+  state[6] += g;
+  state[7] += h;
+}
+
+/// SHA-512, as specified in FIPS 180-4
+pub struct Sha512 {
+  priv engine: Engine512
+}
+
+static SHA512_INIT: [u64, ..8] = [
+  0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+  0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179
+];
+
+impl Sha512 {
+  pub fn new() -> Sha512 {
+    Sha512{ engine: Engine512::new(SHA512_INIT) }
+  }
+}
+
+impl Digest for Sha512 {
+  fn input(&mut self, data: &[u8]) { self.engine.input(data) }
+  fn result(&mut self) -> ~[u8] { self.engine.result(64) }
+  fn reset(&mut self) { self.engine.reset() }
+  fn output_bytes(&self) -> uint { 64 }
+  fn block_bytes(&self) -> uint { 128 }
+}
+
+/// SHA-384, as specified in FIPS 180-4
+pub struct Sha384 {
+  priv engine: Engine512
+}
+
+static SHA384_INIT: [u64, ..8] = [
+  0xcbbb9d5dc1059ed8, 0x629a292a367cd507, 0x9159015a3070dd17, 0x152fecd8f70e5939,
+  0x67332667ffc00b31, 0x8eb44a8768581511, 0xdb0c2e0d64f98fa7, 0x47b5481dbefa4fa4
+];
+
+impl Sha384 {
+  pub fn new() -> Sha384 {
+    Sha384{ engine: Engine512::new(SHA384_INIT) }
+  }
+}
+
+impl Digest for Sha384 {
+  fn input(&mut self, data: &[u8]) { self.engine.input(data) }
+  fn result(&mut self) -> ~[u8] { self.engine.result(48) }
+  fn reset(&mut self) { self.engine.reset() }
+  fn output_bytes(&self) -> uint { 48 }
+  fn block_bytes(&self) -> uint { 128 }
+}
+
+/// SHA-512/256, as specified in FIPS 180-4
+pub struct Sha512Trunc256 {
+  priv engine: Engine512
+}
+
+static SHA512_256_INIT: [u64, ..8] = [
+  0x22312194fc2bf72c, 0x9f555fa3c84c64c2, 0x2393b86b6f53b151, 0x963877195940eabd,
+  0x96283ee2a88effe3, 0xbe5e1e2553863992, 0x2b0199fc2c85b8aa, 0x0eb72ddc81c52ca2
+];
+
+impl Sha512Trunc256 {
+  pub fn new() -> Sha512Trunc256 {
+    Sha512Trunc256{ engine: Engine512::new(SHA512_256_INIT) }
+  }
+}
+
+impl Digest for Sha512Trunc256 {
+  fn input(&mut self, data: &[u8]) { self.engine.input(data) }
+  fn result(&mut self) -> ~[u8] { self.engine.result(32) }
+  fn reset(&mut self) { self.engine.reset() }
+  fn output_bytes(&self) -> uint { 32 }
+  fn block_bytes(&self) -> uint { 128 }
+}
+
+#[cfg(test)]
+mod test_hash {
+  use super::{Digest, HashT, SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256};
+  use serialize::hex::ToHex;
+  use std::slice;
+
+  static ABC: &'static str = "abc";
+  static TWO_BLOCKS: &'static str = "abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+  static TWO_BLOCKS_512: &'static str =
+    "abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+  fn check(hash: HashT, m: &str, expected: &str) {
+    assert_eq!(hash.hash(m.as_bytes()).to_hex(), expected.to_owned());
+  }
+
+  #[test]
+  fn test_sha1() {
+    check(SHA1, "", "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    check(SHA1, ABC, "a9993e364706816aba3e25717850c26c9cd0d89d");
+    check(SHA1, TWO_BLOCKS, "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+  }
+
+  #[test]
+  fn test_sha224() {
+    check(SHA224, "", "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f");
+    check(SHA224, ABC, "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7");
+    check(SHA224, TWO_BLOCKS, "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525");
+  }
+
+  #[test]
+  fn test_sha256() {
+    check(SHA256, "", "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855");
+    check(SHA256, ABC, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    check(SHA256, TWO_BLOCKS, "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1");
+  }
+
+  #[test]
+  fn test_sha384() {
+    check(SHA384, "",
+      "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da\
+       274edebfe76f65fbd51ad2f14898b95b");
+    check(SHA384, ABC,
+      "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed\
+       8086072ba1e7cc2358baeca134c825a7");
+    check(SHA384, TWO_BLOCKS_512,
+      "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712\
+       fcc7c71a557e2db966c3e9fa91746039");
+  }
+
+  #[test]
+  fn test_sha512() {
+    check(SHA512, "",
+      "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+       47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e");
+    check(SHA512, ABC,
+      "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+       2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f");
+    check(SHA512, TWO_BLOCKS_512,
+      "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+       501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909");
+  }
+
+  #[test]
+  fn test_sha512_256() {
+    check(SHA512_256, "", "c672b8d1ef56ed28ab87c3622c5114069bdd3ad7b8f9737498d0c01ecef0967a");
+    check(SHA512_256, ABC, "53048e2681941ef99b2e29b76b4c7dabe4c2d0c634fc6d46e0e2f13107e7af23");
+    check(SHA512_256, TWO_BLOCKS_512, "3928e184fb8690f840da3988121d31be65cb9d3ef83ee6146feac861e19b563a");
+  }
+
+  // The one million "a" messages from the NIST examples
+  #[test]
+  fn test_long_message() {
+    let m = slice::from_elem(1000000, 0x61u8);
+    let expected = [
+      (SHA1, "34aa973cd4c4daa4f61eeb2bdbad27316534016f"),
+      (SHA224, "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67"),
+      (SHA256, "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"),
+      (SHA384, "9d0e1809716474cb086e834e310a4a1ced149e9c00f248527972cec5704c2a5b\
+                07b8b3dc38ecc4ebae97ddd87f3d8985"),
+      (SHA512, "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973eb\
+                de0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b")
+    ];
+    for &(hash, digest) in expected.iter() {
+      assert_eq!(hash.hash(m).to_hex(), digest.to_owned());
+    }
+  }
+
+  #[test]
+  fn test_incremental_input() {
+    for &hash in [SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256].iter() {
+      let m = [0x61u8, ..1000];
+      let mut digest = hash.digest();
+      for chunk in m.chunks(7) { digest.input(chunk); }
+      assert_eq!(digest.result(), hash.hash(m));
+
+      // The digest is reset after producing a result
+      digest.input(ABC.as_bytes());
+      assert_eq!(digest.result(), hash.hash(ABC.as_bytes()));
+    }
+  }
+}
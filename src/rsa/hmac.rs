@@ -0,0 +1,115 @@
+use hash::{Digest, HashT};
+
+/// HMAC from RFC 2104 over any of the supported hash functions
+/// Implements `Digest`, so `result` returns the MAC and leaves it ready for the
+/// next message under the same key
+pub struct Hmac {
+  priv digest: ~Digest,
+  priv inner_pad: ~[u8],
+  priv outer_pad: ~[u8]
+}
+
+impl Hmac {
+  pub fn new(hash: HashT, key: &[u8]) -> Hmac {
+    let mut digest = hash.digest();
+    let block_len = digest.block_bytes();
+
+    // Keys longer than a block are hashed first, and all keys are padded with zeros
+    let mut key = if key.len() > block_len { hash.hash(key) } else { key.to_owned() };
+    let pad_len = block_len - key.len();
+    key.grow(pad_len, &0u8);
+
+    let inner_pad: ~[u8] = key.iter().map(|&byte| byte ^ 0x36).collect();
+    let outer_pad: ~[u8] = key.iter().map(|&byte| byte ^ 0x5c).collect();
+    digest.input(inner_pad);
+    Hmac{ digest: digest, inner_pad: inner_pad, outer_pad: outer_pad }
+  }
+}
+
+impl Digest for Hmac {
+  fn input(&mut self, data: &[u8]) {
+    self.digest.input(data);
+  }
+
+  fn result(&mut self) -> ~[u8] {
+    let inner = self.digest.result();
+    self.digest.input(self.outer_pad);
+    self.digest.input(inner);
+    let mac = self.digest.result();
+    self.digest.input(self.inner_pad);
+    mac
+  }
+
+  fn reset(&mut self) {
+    self.digest.reset();
+    self.digest.input(self.inner_pad);
+  }
+
+  fn output_bytes(&self) -> uint { self.digest.output_bytes() }
+
+  fn block_bytes(&self) -> uint { self.digest.block_bytes() }
+}
+
+/// HMAC of `data` under `key` in one go
+pub fn hmac(hash: HashT, key: &[u8], data: &[u8]) -> ~[u8] {
+  let mut mac = Hmac::new(hash, key);
+  mac.input(data);
+  mac.result()
+}
+
+#[cfg(test)]
+mod test_hmac {
+  use super::{Hmac, hmac};
+  use hash::{Digest, SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256};
+  use serialize::hex::ToHex;
+  use std::slice;
+
+  #[test]
+  fn test_rfc2202_sha1() {
+    assert_eq!(hmac(SHA1, slice::from_elem(20, 0x0bu8), bytes!("Hi There")).to_hex(),
+               ~"b617318655057264e28bc0b6fb378c8ef146be00");
+    assert_eq!(hmac(SHA1, bytes!("Jefe"), bytes!("what do ya want for nothing?")).to_hex(),
+               ~"effcdf6ae5eb2fa2d27416d5f184df9c259a7c79");
+  }
+
+  // Test cases 2 and 6 from RFC 4231, the second with a key longer than a block
+  #[test]
+  fn test_rfc4231() {
+    let m = bytes!("what do ya want for nothing?");
+    assert_eq!(hmac(SHA224, bytes!("Jefe"), m).to_hex(),
+               ~"a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44");
+    assert_eq!(hmac(SHA256, bytes!("Jefe"), m).to_hex(),
+               ~"5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843");
+    assert_eq!(hmac(SHA384, bytes!("Jefe"), m).to_hex(),
+               ~"af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e\
+                 8e2240ca5e69e2c78b3239ecfab21649");
+    assert_eq!(hmac(SHA512, bytes!("Jefe"), m).to_hex(),
+               ~"164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea250554\
+                 9758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737");
+    // Not in RFC 4231, checked against Python's hmac module
+    assert_eq!(hmac(SHA512_256, bytes!("Jefe"), m).to_hex(),
+               ~"6df7b24630d5ccb2ee335407081a87188c221489768fa2020513b2d593359456");
+
+    let key = slice::from_elem(131, 0xaau8);
+    let m = bytes!("Test Using Larger Than Block-Size Key - Hash Key First");
+    assert_eq!(hmac(SHA256, key, m).to_hex(),
+               ~"60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54");
+    assert_eq!(hmac(SHA512, key, m).to_hex(),
+               ~"80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f352\
+                 6b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598");
+  }
+
+  #[test]
+  fn test_reuse() {
+    let mut mac = Hmac::new(SHA256, bytes!("Jefe"));
+    mac.input(bytes!("what do ya "));
+    mac.input(bytes!("want for nothing?"));
+    let first = mac.result();
+    mac.input(bytes!("what do ya want for nothing?"));
+    assert_eq!(mac.result(), first);
+    mac.input(bytes!("discarded"));
+    mac.reset();
+    mac.input(bytes!("what do ya want for nothing?"));
+    assert_eq!(mac.result(), first);
+  }
+}
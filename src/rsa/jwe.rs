@@ -0,0 +1,244 @@
+use std::slice;
+use rand::{Rng, OSRng};
+use collections::TreeMap;
+use serialize::json;
+use serialize::base64::{ToBase64, URL_SAFE};
+use hash::{SHA1, SHA256};
+use gcm::{Gcm, IV_LEN};
+use jws::{decode_segment, decode_header};
+use super::{PublicKey, PrivateKey, PaddingT, OAEPPadding, RsaResult, DecodingError, InvalidCiphertext};
+
+/// JWE key management algorithms from RFC 7518 that wrap the content
+/// encryption key with RSA
+#[deriving(Eq, Clone, Show)]
+pub enum JweAlgorithmT {
+  /// "RSA-OAEP", OAEP with SHA-1 and MGF1 with SHA-1
+  RSAOAEP,
+  /// "RSA-OAEP-256", OAEP with SHA-256 and MGF1 with SHA-256
+  RSAOAEP256
+}
+
+/// JWE content encryption algorithms
+#[deriving(Eq, Clone, Show)]
+pub enum JweEncryptionT {
+  /// AES-128 in GCM mode
+  A128GCM,
+  /// AES-256 in GCM mode
+  A256GCM
+}
+
+impl JweAlgorithmT {
+  /// The "alg" header value
+  pub fn name(&self) -> &'static str {
+    match *self {
+      RSAOAEP    => "RSA-OAEP",
+      RSAOAEP256 => "RSA-OAEP-256"
+    }
+  }
+
+  /// The algorithm with the "alg" header value `name`, if it's one of the RSA ones
+  pub fn from_name(name: &str) -> Option<JweAlgorithmT> {
+    match name {
+      "RSA-OAEP"     => Some(RSAOAEP),
+      "RSA-OAEP-256" => Some(RSAOAEP256),
+      _              => None
+    }
+  }
+
+  fn padding(&self) -> PaddingT {
+    match *self {
+      RSAOAEP    => OAEPPadding(SHA1, None),
+      RSAOAEP256 => OAEPPadding(SHA256, None)
+    }
+  }
+}
+
+impl JweEncryptionT {
+  /// The "enc" header value
+  pub fn name(&self) -> &'static str {
+    match *self {
+      A128GCM => "A128GCM",
+      A256GCM => "A256GCM"
+    }
+  }
+
+  /// The encryption algorithm with the "enc" header value `name`, if it's supported
+  pub fn from_name(name: &str) -> Option<JweEncryptionT> {
+    match name {
+      "A128GCM" => Some(A128GCM),
+      "A256GCM" => Some(A256GCM),
+      _         => None
+    }
+  }
+
+  /// Length of the content encryption key in bytes
+  pub fn key_len(&self) -> uint {
+    match *self {
+      A128GCM => 16,
+      A256GCM => 32
+    }
+  }
+}
+
+/// A JWE whose content has been decrypted and authenticated
+pub struct DecryptedJwe {
+  alg: JweAlgorithmT,
+  enc: JweEncryptionT,
+  header: ~json::Object,
+  plaintext: ~[u8]
+}
+
+// The string member `name` of a header
+fn header_string<'a>(header: &'a json::Object, name: &str) -> RsaResult<&'a str> {
+  match header.find(&name.to_owned()) {
+    Some(&json::String(ref value)) => Ok(value.as_slice()),
+    _                              => Err(DecodingError)
+  }
+}
+
+/// Compact serialization of `plaintext` encrypted for `key`, with only "alg"
+/// and "enc" in the header
+/// A fresh content encryption key and IV are generated for every message
+pub fn encrypt(key: &PublicKey, alg: JweAlgorithmT, enc: JweEncryptionT,
+               plaintext: &[u8]) -> RsaResult<~str> {
+  encrypt_with_header(key, alg, enc, ~TreeMap::new(), plaintext)
+}
+
+/// Like `encrypt`, with other header parameters such as "kid" or "cty"
+/// Any "alg" or "enc" already in `header` is replaced
+pub fn encrypt_with_header(key: &PublicKey, alg: JweAlgorithmT, enc: JweEncryptionT,
+                           header: ~json::Object, plaintext: &[u8]) -> RsaResult<~str> {
+  let mut header = header;
+  header.insert(~"alg", json::String(alg.name().to_owned()));
+  header.insert(~"enc", json::String(enc.name().to_owned()));
+  let encoded_header = json::Object(header).to_str().as_bytes().to_base64(URL_SAFE);
+
+  let mut rng = OSRng::new();
+  let mut cek = slice::from_elem(enc.key_len(), 0u8);
+  rng.fill_bytes(cek.as_mut_slice());
+  let mut iv = [0u8, ..IV_LEN];
+  rng.fill_bytes(iv);
+
+  let encrypted_key = try!(key.encrypt_bytes(cek, alg.padding()));
+  let gcm = Gcm::new(cek).unwrap();
+  // The encoded header is the additional authenticated data
+  let (ciphertext, tag) = gcm.encrypt(iv, encoded_header.as_bytes(), plaintext);
+  Ok(format!("{}.{}.{}.{}.{}", encoded_header, encrypted_key.to_base64(URL_SAFE),
+             iv.to_base64(URL_SAFE), ciphertext.to_base64(URL_SAFE), tag.to_base64(URL_SAFE)))
+}
+
+/// Decrypt a compact JWE whose key was wrapped with one of the `allowed` algorithms
+/// Other algorithms, unsupported "enc" values, compressed content and "crit"
+/// extensions fail with `InvalidCiphertext`, as does any tampering
+pub fn decrypt(key: &PrivateKey, allowed: &[JweAlgorithmT], token: &str) -> RsaResult<DecryptedJwe> {
+  let parts: ~[&str] = token.split('.').collect();
+  if parts.len() != 5 { return Err(DecodingError) }
+  let header = try!(decode_header(parts[0]));
+  let alg = match JweAlgorithmT::from_name(try!(header_string(header, "alg"))) {
+    Some(alg) if allowed.contains(&alg) => alg,
+    _                                   => return Err(InvalidCiphertext)
+  };
+  let enc = match JweEncryptionT::from_name(try!(header_string(header, "enc"))) {
+    Some(enc) => enc,
+    None      => return Err(InvalidCiphertext)
+  };
+  if header.contains_key(&~"zip") || header.contains_key(&~"crit") { return Err(InvalidCiphertext) }
+
+  let encrypted_key = try!(decode_segment(parts[1]));
+  let iv = try!(decode_segment(parts[2]));
+  let ciphertext = try!(decode_segment(parts[3]));
+  let tag = try!(decode_segment(parts[4]));
+  if iv.len() != IV_LEN { return Err(DecodingError) }
+
+  // If the key doesn't unwrap, carry on with a random one so the failure
+  // looks the same as a bad tag, as RFC 7516 section 11.5 recommends
+  let cek = key.decrypt_bytes(encrypted_key, alg.padding()).unwrap_or(~[]);
+  let cek = if cek.len() == enc.key_len() {
+    cek
+  } else {
+    let mut cek = slice::from_elem(enc.key_len(), 0u8);
+    OSRng::new().fill_bytes(cek.as_mut_slice());
+    cek
+  };
+  let gcm = Gcm::new(cek).unwrap();
+  match gcm.decrypt(iv, parts[0].as_bytes(), ciphertext, tag) {
+    Some(plaintext) => Ok(DecryptedJwe{ alg: alg, enc: enc, header: header, plaintext: plaintext }),
+    None            => Err(InvalidCiphertext)
+  }
+}
+
+#[cfg(test)]
+mod test_jwe {
+  use super::{encrypt, encrypt_with_header, decrypt, RSAOAEP, RSAOAEP256, A128GCM, A256GCM};
+  use super::super::{PrivateKey, DecodingError, InvalidCiphertext};
+  use test_keys::PKCS1_PRIVATE_PEM;
+  use collections::TreeMap;
+  use serialize::json;
+
+  static PLAINTEXT: &'static str = "{\"sub\":\"partner-42\",\"scope\":\"orders\"}";
+
+  // Tokens for PLAINTEXT encrypted for the key above by Python's cryptography package
+  static OAEP_A128GCM_TOKEN: &'static str =
+    "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkExMjhHQ00ifQ.\
+     SINVoCyHK_fLkFreSMqdXWr6t3n7kWMFCZJUxVO6P0-c1yi3Xnu82QpuFDsuI3ocNPryTuIko7VdlZSrn2J1V5yF3_0N\
+     1Zx-A0mJS28bQMNsF5EywAfuQVQtEuJGHX8IvO_1ioA0B4HSe13iQUFzMEAQiTPeSYQCM3EqPY3bg94.\
+     BlszCGjXtwLsKqol.zH6P0dHAA8e0t9m9a0ZO3KfOLD6FCrekLlQJgj2OPWJ5mDKBOw.RKgksaovPdfs9nlBm4x2WA";
+  static OAEP256_A256GCM_TOKEN: &'static str =
+    "eyJhbGciOiJSU0EtT0FFUC0yNTYiLCJlbmMiOiJBMjU2R0NNIiwia2lkIjoicGFydG5lciJ9.\
+     njiYBIx70UsUD03D3_j9vqLW52xr7Bt1RHHB_-v8wzmLGThUaxeDyIvpCIUi1-UN-dp75quiGOhq0Bdp9zcChpMN0wS7\
+     YS74OvpukYgSVeaY7Pvbwa137ylzAQwDpYB6JpAbLBE9IHW3KxU8fEIZv8c2YG-OLNlaRVLqdqpJCGo.\
+     uh7sOf5O6EU65Q-C.ocX0_bIBHMtyilqGCcT7yS7yI1Fz7QMJ8Q-BzKoUjGf7Nm3JkA.EuAa6KvIUWVQFXKYz-nRPA";
+
+  #[test]
+  fn test_decrypt() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let decrypted = decrypt(&private, [RSAOAEP], OAEP_A128GCM_TOKEN).unwrap();
+    assert_eq!(decrypted.enc, A128GCM);
+    assert_eq!(decrypted.plaintext, PLAINTEXT.as_bytes().to_owned());
+
+    let decrypted = decrypt(&private, [RSAOAEP, RSAOAEP256], OAEP256_A256GCM_TOKEN).unwrap();
+    assert_eq!(decrypted.alg, RSAOAEP256);
+    assert_eq!(decrypted.enc, A256GCM);
+    assert!(decrypted.header.find(&~"kid") == Some(&json::String(~"partner")));
+    assert_eq!(decrypted.plaintext, PLAINTEXT.as_bytes().to_owned());
+
+    assert_eq!(decrypt(&private, [RSAOAEP256], OAEP_A128GCM_TOKEN).err(), Some(InvalidCiphertext));
+  }
+
+  #[test]
+  fn test_round_trip() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let public = private.public_key();
+    for &(alg, enc) in [(RSAOAEP, A128GCM), (RSAOAEP256, A256GCM)].iter() {
+      let token = encrypt(&public, alg, enc, bytes!("for partners only")).unwrap();
+      let decrypted = decrypt(&private, [alg], token).unwrap();
+      assert_eq!(decrypted.plaintext, bytes!("for partners only").to_owned());
+    }
+
+    let mut header = ~TreeMap::new();
+    header.insert(~"cty", json::String(~"JWT"));
+    let token = encrypt_with_header(&public, RSAOAEP256, A128GCM, header, []).unwrap();
+    let decrypted = decrypt(&private, [RSAOAEP256], token).unwrap();
+    assert!(decrypted.header.find(&~"cty") == Some(&json::String(~"JWT")));
+    assert!(decrypted.plaintext.is_empty());
+  }
+
+  #[test]
+  fn test_tampering() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let parts: ~[&str] = OAEP_A128GCM_TOKEN.split('.').collect();
+
+    // The header is authenticated: {"alg":"RSA-OAEP","enc":"A128GCM","kid":"x"}
+    let header = "eyJhbGciOiJSU0EtT0FFUCIsImVuYyI6IkExMjhHQ00iLCJraWQiOiJ4In0";
+    let token = format!("{}.{}.{}.{}.{}", header, parts[1], parts[2], parts[3], parts[4]);
+    assert_eq!(decrypt(&private, [RSAOAEP], token).err(), Some(InvalidCiphertext));
+
+    // A key that doesn't unwrap fails the same way as a bad tag
+    let token = format!("{}.{}.{}.{}.{}", parts[0], parts[3], parts[2], parts[3], parts[4]);
+    assert_eq!(decrypt(&private, [RSAOAEP], token).err(), Some(InvalidCiphertext));
+    let token = format!("{}.{}.{}.{}.{}", parts[0], parts[1], parts[2], parts[3], parts[1].slice_to(22));
+    assert_eq!(decrypt(&private, [RSAOAEP], token).err(), Some(InvalidCiphertext));
+
+    assert_eq!(decrypt(&private, [RSAOAEP], parts.slice_to(4).connect(".")).err(), Some(DecodingError));
+  }
+}
@@ -0,0 +1,310 @@
+use std::cmp::max;
+use std::num::One;
+use collections::TreeMap;
+use serialize::json;
+use serialize::json::Json;
+use serialize::base64::{ToBase64, FromBase64, URL_SAFE};
+use bignum::BigUint;
+use hash::HashT;
+use super::{PublicKey, PrivateKey, OtherPrimeInfo, RsaResult, DecodingError, InvalidKey,
+            i2osp, os2ip, modulus_len};
+
+// Base64url without padding of the big-endian bytes of `m`, always at least one byte
+fn encode_integer(m: &BigUint) -> Json {
+  let bytes = i2osp(m, max(1, modulus_len(m))).unwrap();
+  json::String(bytes.to_base64(URL_SAFE))
+}
+
+// The string member `name` of `obj`, if there is one
+fn find_string<'a>(obj: &'a json::Object, name: &str) -> RsaResult<Option<&'a str>> {
+  match obj.find(&name.to_owned()) {
+    Some(&json::String(ref value)) => Ok(Some(value.as_slice())),
+    Some(_)                        => Err(DecodingError),
+    None                           => Ok(None)
+  }
+}
+
+// The integer member `name` of `obj`, if there is one
+fn find_integer(obj: &json::Object, name: &str) -> RsaResult<Option<BigUint>> {
+  match try!(find_string(obj, name)) {
+    Some(value) => {
+      // Padding isn't allowed in JWK values
+      if value.contains_char('=') { return Err(DecodingError) }
+      let bytes = try!(value.from_base64().map_err(|_| DecodingError));
+      if bytes.is_empty() { return Err(DecodingError) }
+      Ok(Some(os2ip(bytes)))
+    },
+    None => Ok(None)
+  }
+}
+
+fn require<T>(value: Option<T>) -> RsaResult<T> {
+  match value {
+    Some(value) => Ok(value),
+    None        => Err(DecodingError)
+  }
+}
+
+// The members of an RSA JWK object
+fn rsa_members<'a>(jwk: &'a Json) -> RsaResult<&'a json::Object> {
+  let obj = match *jwk {
+    json::Object(ref obj) => &**obj,
+    _                     => return Err(DecodingError)
+  };
+  match try!(find_string(obj, "kty")) {
+    Some("RSA") => Ok(obj),
+    Some(_)     => Err(InvalidKey),
+    None        => Err(DecodingError)
+  }
+}
+
+// The members shared by public and private keys
+fn public_members(n: &BigUint, e: &BigUint) -> ~json::Object {
+  let mut obj = ~TreeMap::new();
+  obj.insert(~"kty", json::String(~"RSA"));
+  obj.insert(~"n", encode_integer(n));
+  obj.insert(~"e", encode_integer(e));
+  obj
+}
+
+impl PublicKey {
+  /// JWK from RFC 7517 with the "kty", "n" and "e" members
+  pub fn to_jwk(&self) -> Json {
+    json::Object(public_members(&self.n, &self.e))
+  }
+
+  /// Public key from an RSA JWK
+  /// Other members such as "kid" and "use" are ignored, and private members may be present
+  pub fn from_jwk(jwk: &Json) -> RsaResult<PublicKey> {
+    let obj = try!(rsa_members(jwk));
+    let n = try!(require(try!(find_integer(obj, "n"))));
+    let e = try!(require(try!(find_integer(obj, "e"))));
+    Ok(PublicKey::new(n, e))
+  }
+
+  /// JWK thumbprint from RFC 7638, base64url encoded, e.g. for use as a "kid"
+  pub fn jwk_thumbprint(&self, hash: HashT) -> ~str {
+    // The required members in lexicographic order with no whitespace
+    let canonical = self.to_jwk().to_str();
+    hash.hash(canonical.as_bytes()).to_base64(URL_SAFE)
+  }
+}
+
+impl PrivateKey {
+  /// JWK from RFC 7517 including the private members
+  /// The CRT members are only present if the key knows its prime factors, with
+  /// any primes after the first two in "oth"
+  pub fn to_jwk(&self) -> Json {
+    let mut obj = public_members(&self.n, &self.e);
+    obj.insert(~"d", encode_integer(&self.d));
+    match self.crt {
+      Some(ref crt) => {
+        obj.insert(~"p", encode_integer(&crt.p));
+        obj.insert(~"q", encode_integer(&crt.q));
+        obj.insert(~"dp", encode_integer(&crt.dp));
+        obj.insert(~"dq", encode_integer(&crt.dq));
+        obj.insert(~"qi", encode_integer(&crt.qinv));
+        if !crt.other_primes.is_empty() {
+          let oth = crt.other_primes.iter().map(|info| {
+            let mut member = ~TreeMap::new();
+            member.insert(~"r", encode_integer(&info.r));
+            member.insert(~"d", encode_integer(&info.d));
+            member.insert(~"t", encode_integer(&info.t));
+            json::Object(member)
+          }).collect();
+          obj.insert(~"oth", json::List(oth));
+        }
+      },
+      None          => {}
+    }
+    json::Object(obj)
+  }
+
+  /// Private key from an RSA JWK with the "d" member
+  /// The CRT members are optional, but must all be present and consistent if any
+  /// are, and "oth" is only allowed along with them
+  pub fn from_jwk(jwk: &Json) -> RsaResult<PrivateKey> {
+    let obj = try!(rsa_members(jwk));
+    let n = try!(require(try!(find_integer(obj, "n"))));
+    let e = try!(require(try!(find_integer(obj, "e"))));
+    let d = try!(require(try!(find_integer(obj, "d"))));
+
+    // Each member of "oth" is a prime after the first two, with its exponent and coefficient
+    let mut others = ~[];
+    match obj.find(&~"oth") {
+      Some(&json::List(ref oth)) => {
+        if oth.is_empty() { return Err(DecodingError) }
+        for member in oth.iter() {
+          let member = match *member {
+            json::Object(ref member) => &**member,
+            _                        => return Err(DecodingError)
+          };
+          let r = try!(require(try!(find_integer(member, "r"))));
+          let d = try!(require(try!(find_integer(member, "d"))));
+          let t = try!(require(try!(find_integer(member, "t"))));
+          others.push(OtherPrimeInfo{ r: r, d: d, t: t });
+        }
+      },
+      Some(_)                    => return Err(DecodingError),
+      None                       => {}
+    }
+
+    let p = try!(find_integer(obj, "p"));
+    let q = try!(find_integer(obj, "q"));
+    let dp = try!(find_integer(obj, "dp"));
+    let dq = try!(find_integer(obj, "dq"));
+    let qi = try!(find_integer(obj, "qi"));
+    match (p, q, dp, dq, qi) {
+      (None, None, None, None, None) if others.is_empty() => Ok(PrivateKey::new(n, e, d)),
+      (Some(p), Some(q), Some(dp), Some(dq), Some(qi)) => {
+        let mut factors = ~[p, q];
+        for info in others.iter() { factors.push(info.r.clone()); }
+        if factors.iter().fold(One::one(), |product: BigUint, r| product * *r) != n { return Err(InvalidKey) }
+        let key = try!(PrivateKey::from_multi_primes(factors, e, d));
+        let consistent = match key.crt {
+          Some(ref crt) => crt.dp == dp && crt.dq == dq && crt.qinv == qi && crt.other_primes == others,
+          None          => false
+        };
+        if consistent { Ok(key) } else { Err(InvalidKey) }
+      },
+      _ => Err(DecodingError)
+    }
+  }
+}
+
+/// Public keys from the "keys" array of a JWK Set, along with their "kid"
+/// Keys of other types, such as elliptic curve keys, are skipped
+pub fn public_keys_from_jwks(jwks: &Json) -> RsaResult<~[(Option<~str>, PublicKey)]> {
+  let keys = match *jwks {
+    json::Object(ref obj) => match obj.find(&~"keys") {
+      Some(&json::List(ref keys)) => keys,
+      _                           => return Err(DecodingError)
+    },
+    _                     => return Err(DecodingError)
+  };
+
+  let mut result = ~[];
+  for jwk in keys.iter() {
+    match PublicKey::from_jwk(jwk) {
+      Ok(key)          => {
+        let kid = match *jwk {
+          json::Object(ref obj) => try!(find_string(&**obj, "kid")).map(|kid| kid.to_owned()),
+          _                     => None
+        };
+        result.push((kid, key));
+      },
+      Err(InvalidKey) => {},
+      Err(err)        => return Err(err)
+    }
+  }
+  Ok(result)
+}
+
+#[cfg(test)]
+mod test_jwk {
+  use super::public_keys_from_jwks;
+  use super::super::{PublicKey, PrivateKey, DecodingError, InvalidKey, KeySize, DefaultExponent,
+                     gen_multi_prime_keys};
+  use hash::SHA256;
+  use serialize::json;
+
+  // The example key from RFC 7638 section 3.1
+  static RFC7638_JWK: &'static str =
+    "{\"kty\":\"RSA\",\"n\":\"0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86z\
+     wu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5Js\
+     GY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMic\
+     AtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-\
+     bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csF\
+     Cur-kEgU8awapJzKnqDKgw\",\
+     \"e\":\"AQAB\",\"alg\":\"RS256\",\"kid\":\"2011-04-29\"}";
+
+  // The 1024-bit OpenSSL test key as a JWK, with its members in sorted order
+  static OPENSSL_PRIVATE_JWK: &'static str =
+    "{\"d\":\"D7ERtJXc3B0py8KrxjmY_xBeTFyJKZVoBhDjtJcb5vPbrKSxTd32oSyVpNAf_c9b\
+     cB1cdKMChGKm_DQmJhk2Q-LHeTjf5X89nqDraudsFzaEjsBu7tEbU-NrrvDrJ1bD\
+     tXOTjASwlHhKVLLK6HkJGxyrPi918VLze-q8vzZ0myE\",\
+     \"dp\":\"oSYmh9XASwXzQc_XdsbrlecJUr-RZW9fT0l7D8BTsXFE2MaEZH7Jqqf1JaZdClIz\
+     zpKzO4Qw2HZ6NPQx4g7ckQ\",\
+     \"dq\":\"UvzYZIAdPJromO0C8paLEjpilHI4is74m9psWNOP_FOYIQEKldNDX61ULNVHfwoq\
+     AXo_4T9vIzcpQC_4bxt8Vw\",\
+     \"e\":\"AQAB\",\
+     \"kty\":\"RSA\",\
+     \"n\":\"rjbKbQIDpFJO4fdkm9Yo4ZJWrjOAEfqa-TsZsczCuVfvBYYMKtDLRU-dh21zqLc9\
+     V65R4Na4ddZtJoQzRX2m-gabmYPE4udH9bUTso4Jx_ZU6w-lwEaATT9dFzNyHgxz\
+     A3nxz1fEJHQHFbeBmE0oaock0S9BdHOC0R9uazCcZb8\",\
+     \"p\":\"5yeVh6LD4KKdXgghs1RP1mrTCK8nWFCTpTY5H_k0t-BvCt8-xvYiQ_PIvXMB6hws\
+     UEuVsCFUSoR2BT8rHgUdEQ\",\
+     \"q\":\"wPBwCtRu8mNYUQ70x9gXsROKsSbxDVF3IVkHP9VcQi684jbLXLGutd-mcGy6Ngzj\
+     G7pyliXyPP6UE_RFs5-Vzw\",\
+     \"qi\":\"3HFHN53BJdLa4nbBEcPRQUe6Yy_T05sZw3081Wb85P30VM6v6u4yZENTglItDjr9\
+     zg8x5k9H5nPlj-x2uTw3Ng\"}";
+
+  #[test]
+  fn test_rfc7638_thumbprint() {
+    let public = PublicKey::from_jwk(&json::from_str(RFC7638_JWK).unwrap()).unwrap();
+    assert_eq!(public.key_size, 2048);
+    assert_eq!(public.jwk_thumbprint(SHA256), ~"NzbLsXh8uDCcd-6MNwXF4W_7noWXFZAfHkxZsRGC9Xs");
+  }
+
+  #[test]
+  fn test_private_key_jwk() {
+    let jwk = json::from_str(OPENSSL_PRIVATE_JWK).unwrap();
+    let private = PrivateKey::from_jwk(&jwk).unwrap();
+    assert_eq!(private.to_jwk().to_str(), OPENSSL_PRIVATE_JWK.to_owned());
+
+    // The public members alone are the public key
+    let public = PublicKey::from_jwk(&jwk).unwrap();
+    assert_eq!(public.n, private.n);
+    assert_eq!(public.jwk_thumbprint(SHA256), ~"QXBXd8y3DIoetRgXxzSrcq_dMLXS0EoP8zfzMhnjx0A");
+
+    // Without the CRT members
+    let non_crt = PrivateKey::new(private.n.clone(), private.e.clone(), private.d.clone());
+    let decoded = PrivateKey::from_jwk(&non_crt.to_jwk()).unwrap();
+    assert!(decoded.crt.is_none());
+    assert_eq!(decoded.d, private.d);
+  }
+
+  #[test]
+  fn test_multi_prime_key_jwk() {
+    let (_, private) = gen_multi_prime_keys(KeySize(768), DefaultExponent, 3).unwrap();
+    let jwk = private.to_jwk();
+    let oth = match jwk {
+      json::Object(ref obj) => obj.find(&~"oth").map(|oth| oth.to_str()),
+      _                     => None
+    };
+    assert!(oth.unwrap().starts_with("[{\"d\":"));
+    let decoded = PrivateKey::from_jwk(&jwk).unwrap();
+    assert_eq!(decoded.crt.get_ref().other_primes, private.crt.get_ref().other_primes);
+    assert_eq!(decoded.to_jwk().to_str(), jwk.to_str());
+  }
+
+  #[test]
+  fn test_jwk_errors() {
+    let parse = |s: &str| PrivateKey::from_jwk(&json::from_str(s).unwrap()).err();
+    // Not an RSA key
+    assert_eq!(parse("{\"kty\":\"EC\",\"crv\":\"P-256\"}"), Some(InvalidKey));
+    // Missing "d"
+    assert_eq!(parse("{\"kty\":\"RSA\",\"n\":\"AQAB\",\"e\":\"AQAB\"}"), Some(DecodingError));
+    // Padded base64
+    assert_eq!(parse("{\"kty\":\"RSA\",\"n\":\"AQA=\",\"e\":\"AQAB\",\"d\":\"AQAB\"}"), Some(DecodingError));
+    // Only some of the CRT members
+    assert_eq!(parse("{\"kty\":\"RSA\",\"n\":\"AQAB\",\"e\":\"AQAB\",\"d\":\"AQAB\",\"p\":\"AQAB\"}"),
+               Some(DecodingError));
+    // Other primes without the CRT members
+    assert_eq!(parse("{\"kty\":\"RSA\",\"n\":\"AQAB\",\"e\":\"AQAB\",\"d\":\"AQAB\",\"oth\":[]}"),
+               Some(DecodingError));
+  }
+
+  #[test]
+  fn test_jwks() {
+    let mut jwks = ~"{\"keys\":[{\"kty\":\"EC\",\"crv\":\"P-256\"},";
+    jwks.push_str(RFC7638_JWK);
+    jwks.push_str("]}");
+    let keys = public_keys_from_jwks(&json::from_str(jwks).unwrap()).unwrap();
+    assert_eq!(keys.len(), 1);
+    let (ref kid, ref key) = keys[0];
+    assert_eq!(*kid, Some(~"2011-04-29"));
+    assert_eq!(key.key_size, 2048);
+    assert_eq!(public_keys_from_jwks(&json::from_str("[]").unwrap()).err(), Some(DecodingError));
+  }
+}
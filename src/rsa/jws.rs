@@ -0,0 +1,230 @@
+use std::str;
+use collections::TreeMap;
+use serialize::json;
+use serialize::base64::{ToBase64, FromBase64, URL_SAFE};
+use hash::{SHA256, SHA384, SHA512};
+use super::{PublicKey, PrivateKey, PSSParams, RsaResult, DecodingError, InvalidSignature, or_error};
+
+/// JWS algorithms from RFC 7518 that use RSA keys
+#[deriving(Eq, Clone, Show)]
+pub enum JwsAlgorithmT {
+  /// RSASSA-PKCS1-v1_5 with SHA-256
+  RS256,
+  /// RSASSA-PKCS1-v1_5 with SHA-384
+  RS384,
+  /// RSASSA-PKCS1-v1_5 with SHA-512
+  RS512,
+  /// RSASSA-PSS with SHA-256, MGF1 with SHA-256 and a 32 byte salt
+  PS256
+}
+
+impl JwsAlgorithmT {
+  /// The "alg" header value
+  pub fn name(&self) -> &'static str {
+    match *self {
+      RS256 => "RS256",
+      RS384 => "RS384",
+      RS512 => "RS512",
+      PS256 => "PS256"
+    }
+  }
+
+  /// The algorithm with the "alg" header value `name`, if it's one of the RSA ones
+  pub fn from_name(name: &str) -> Option<JwsAlgorithmT> {
+    match name {
+      "RS256" => Some(RS256),
+      "RS384" => Some(RS384),
+      "RS512" => Some(RS512),
+      "PS256" => Some(PS256),
+      _       => None
+    }
+  }
+
+  fn sign(&self, key: &PrivateKey, input: &[u8]) -> RsaResult<~[u8]> {
+    match *self {
+      RS256 => key.sign(SHA256, input),
+      RS384 => key.sign(SHA384, input),
+      RS512 => key.sign(SHA512, input),
+      PS256 => key.sign_pss(&PSSParams::new(SHA256), input)
+    }
+  }
+
+  fn verify(&self, key: &PublicKey, input: &[u8], signature: &[u8]) -> RsaResult<()> {
+    match *self {
+      RS256 => key.verify(SHA256, input, signature),
+      RS384 => key.verify(SHA384, input, signature),
+      RS512 => key.verify(SHA512, input, signature),
+      PS256 => key.verify_pss(&PSSParams::new(SHA256), input, signature)
+    }
+  }
+}
+
+/// A JWS whose signature has been checked
+pub struct VerifiedJws {
+  alg: JwsAlgorithmT,
+  header: ~json::Object,
+  payload: ~[u8]
+}
+
+/// Base64url without padding, the only encoding allowed in the compact
+/// serializations of JWS and JWE
+pub fn decode_segment(segment: &str) -> RsaResult<~[u8]> {
+  let valid = segment.chars().all(|c| (c >= 'a' && c <= 'z') || (c >= 'A' && c <= 'Z') ||
+                                      (c >= '0' && c <= '9') || c == '-' || c == '_');
+  if !valid { return Err(DecodingError) }
+  segment.from_base64().map_err(|_| DecodingError)
+}
+
+/// The JSON object in a base64url encoded header segment
+pub fn decode_header(segment: &str) -> RsaResult<~json::Object> {
+  let bytes = try!(decode_segment(segment));
+  let text = try!(or_error(str::from_utf8(bytes), DecodingError));
+  match json::from_str(text) {
+    Ok(json::Object(header)) => Ok(header),
+    _                        => Err(DecodingError)
+  }
+}
+
+// The three segments of a compact JWS
+fn split_compact<'a>(token: &'a str) -> RsaResult<~[&'a str]> {
+  let parts: ~[&str] = token.split('.').collect();
+  if parts.len() != 3 { return Err(DecodingError) }
+  Ok(parts)
+}
+
+/// Compact serialization of `payload` signed with `alg`, with only "alg" in the header
+pub fn sign(key: &PrivateKey, alg: JwsAlgorithmT, payload: &[u8]) -> RsaResult<~str> {
+  sign_with_header(key, alg, ~TreeMap::new(), payload)
+}
+
+/// Like `sign`, with other header parameters such as "kid" or "typ"
+/// Any "alg" already in `header` is replaced
+pub fn sign_with_header(key: &PrivateKey, alg: JwsAlgorithmT, header: ~json::Object,
+                        payload: &[u8]) -> RsaResult<~str> {
+  let mut header = header;
+  header.insert(~"alg", json::String(alg.name().to_owned()));
+  let encoded_header = json::Object(header).to_str();
+  let input = format!("{}.{}", encoded_header.as_bytes().to_base64(URL_SAFE), payload.to_base64(URL_SAFE));
+  let signature = try!(alg.sign(key, input.as_bytes()));
+  Ok(format!("{}.{}", input, signature.to_base64(URL_SAFE)))
+}
+
+/// Verify a compact JWS signed by `key` with one of the `allowed` algorithms
+/// The header's "alg" is never trusted on its own: "none", HMAC and any other
+/// algorithm not in `allowed` fail with `InvalidSignature`, as do headers with
+/// "crit" extensions, none of which are understood
+/// Claims in the payload such as "exp" are left to the caller
+pub fn verify(key: &PublicKey, allowed: &[JwsAlgorithmT], token: &str) -> RsaResult<VerifiedJws> {
+  let parts = try!(split_compact(token));
+  let header = try!(decode_header(parts[0]));
+  let alg = match header.find(&~"alg") {
+    Some(&json::String(ref name)) => JwsAlgorithmT::from_name(name.as_slice()),
+    _                             => return Err(DecodingError)
+  };
+  let alg = match alg {
+    Some(alg) if allowed.contains(&alg) => alg,
+    _                                   => return Err(InvalidSignature)
+  };
+  if header.contains_key(&~"crit") { return Err(InvalidSignature) }
+
+  let payload = try!(decode_segment(parts[1]));
+  let signature = try!(decode_segment(parts[2]));
+  let input = token.slice_to(parts[0].len() + 1 + parts[1].len());
+  try!(alg.verify(key, input.as_bytes(), signature));
+  Ok(VerifiedJws{ alg: alg, header: header, payload: payload })
+}
+
+/// The header of a compact JWS without checking the signature, e.g. to look
+/// up the key named by its "kid"
+pub fn unverified_header(token: &str) -> RsaResult<~json::Object> {
+  let parts = try!(split_compact(token));
+  decode_header(parts[0])
+}
+
+#[cfg(test)]
+mod test_jws {
+  use super::{sign, sign_with_header, verify, unverified_header, RS256, RS384, RS512, PS256};
+  use super::super::{PrivateKey, DecodingError, InvalidSignature};
+  use test_keys::PKCS1_PRIVATE_PEM;
+  use collections::TreeMap;
+  use serialize::json;
+
+  static PAYLOAD: &'static str = "{\"sub\":\"1234567890\",\"admin\":true}";
+
+  // Tokens for PAYLOAD made with the key above by Python's cryptography package
+  static RS256_TOKEN: &'static str =
+    "eyJhbGciOiJSUzI1NiIsImtpZCI6ImdhdGV3YXktMSJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9.\
+     GgedZJpoF7q0VVU3NPE565sK_q_c2-oBL0SVOd-d19pxi55GuIZEhzrsGxJV-NWvyxR6d6r-ZMdlGGhGJYXlgaIAfS4s\
+     0QXM3fOGJjJVtf9QKVWS5nMufyjFLjuTiPaP01ACFPcRvGfNBnNa_XH9eEGr7LTgJcKM_zOPX48Sen0";
+  static RS512_TOKEN: &'static str =
+    "eyJhbGciOiJSUzUxMiJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9.\
+     Sgo3V7ezPEUmavnhjCOjU5d8YbDeI_vX8-6Y-KcWeogWD2iTdihzXFZad3w5T1sQgdJaWRUzW71La3GrJ_9Jgtrj1SW6\
+     fQU9-AusramcFAVpGmP9CdTr6VU4CBT6bmJaffQt81P4LHyaqEdlm6VxwofmwItUelSDshGPoVnFutE";
+  static PS256_TOKEN: &'static str =
+    "eyJhbGciOiJQUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9.\
+     Ugr0OZK4kLbptHznb4o-QSzMiyOrSXQlkh1V87keUZuWB-V5vgnQl5PE44237LYDv6BujQd8sP2Sff-xLPn3_8k4XwNZ\
+     nvlUkq00YWr_T5oViKU8qZ_-BnEtEIQlSEyZDPqg8p0l62T6Zcfm4CS1bpuM2UzYHDeP4Ux56K8oFfw";
+  // HS256 with the public key PEM as the HMAC secret, the classic algorithm confusion attack
+  static HS256_TOKEN: &'static str =
+    "eyJhbGciOiJIUzI1NiIsInR5cCI6IkpXVCJ9.eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9.\
+     WuzzLy7HZItx_GNSfpbtebpp0qVvPqrPDhIluICZuNc";
+  static NONE_TOKEN: &'static str = "eyJhbGciOiJub25lIn0.eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9.";
+
+  #[test]
+  fn test_sign() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let mut header = ~TreeMap::new();
+    header.insert(~"kid", json::String(~"gateway-1"));
+    header.insert(~"alg", json::String(~"none"));
+    // RSASSA-PKCS1-v1_5 is deterministic
+    assert_eq!(sign_with_header(&private, RS256, header, PAYLOAD.as_bytes()).unwrap(), RS256_TOKEN.to_owned());
+
+    let public = private.public_key();
+    for &alg in [RS256, RS384, RS512, PS256].iter() {
+      let token = sign(&private, alg, bytes!("payload")).unwrap();
+      let verified = verify(&public, [alg], token).unwrap();
+      assert_eq!(verified.alg, alg);
+      assert_eq!(verified.payload, bytes!("payload").to_owned());
+    }
+  }
+
+  #[test]
+  fn test_verify() {
+    let public = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap().public_key();
+    let verified = verify(&public, [RS256, PS256], RS256_TOKEN).unwrap();
+    assert_eq!(verified.alg, RS256);
+    assert_eq!(verified.payload, PAYLOAD.as_bytes().to_owned());
+    assert!(verified.header.find(&~"kid") == Some(&json::String(~"gateway-1")));
+    assert_eq!(verify(&public, [RS512], RS512_TOKEN).unwrap().alg, RS512);
+    assert_eq!(verify(&public, [PS256], PS256_TOKEN).unwrap().alg, PS256);
+
+    // Only the algorithms the caller allows are accepted
+    assert_eq!(verify(&public, [PS256], RS256_TOKEN).err(), Some(InvalidSignature));
+    assert_eq!(verify(&public, [RS256, RS384, RS512, PS256], HS256_TOKEN).err(), Some(InvalidSignature));
+    assert_eq!(verify(&public, [RS256, RS384, RS512, PS256], NONE_TOKEN).err(), Some(InvalidSignature));
+
+    // A different payload under the same signature
+    // {"sub":"1234567890","admin":false}
+    let tampered = RS256_TOKEN.replace("eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOnRydWV9",
+                                       "eyJzdWIiOiIxMjM0NTY3ODkwIiwiYWRtaW4iOmZhbHNlfQ");
+    assert_eq!(verify(&public, [RS256], tampered).err(), Some(InvalidSignature));
+  }
+
+  #[test]
+  fn test_malformed() {
+    let public = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap().public_key();
+    assert_eq!(verify(&public, [RS256], "eyJhbGciOiJSUzI1NiJ9.e30").err(), Some(DecodingError));
+    // Padding and the standard base64 alphabet aren't allowed
+    assert_eq!(verify(&public, [RS256], "eyJhbGciOiJSUzI1NiJ9.e30=.AA").err(), Some(DecodingError));
+    assert_eq!(verify(&public, [RS256], "eyJhbGciOiJSUzI1NiJ9.e30.A+").err(), Some(DecodingError));
+    // The header must be a JSON object with an "alg"
+    assert_eq!(verify(&public, [RS256], "WzFd.e30.AA").err(), Some(DecodingError));
+    assert_eq!(verify(&public, [RS256], "e30.e30.AA").err(), Some(DecodingError));
+    // {"alg":"RS256","crit":["exp"]}
+    assert_eq!(verify(&public, [RS256], "eyJhbGciOiJSUzI1NiIsImNyaXQiOlsiZXhwIl19.e30.AA").err(),
+               Some(InvalidSignature));
+
+    let header = unverified_header(PS256_TOKEN).unwrap();
+    assert!(header.find(&~"typ") == Some(&json::String(~"JWT")));
+  }
+}
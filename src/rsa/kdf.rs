@@ -0,0 +1,167 @@
+use std::slice;
+use std::cmp::min;
+use std::iter::range_step;
+use hash::{Digest, HashT, SHA512};
+use hmac::Hmac;
+use blowfish::Blowfish;
+
+/// PBKDF2 from RFC 8018 with HMAC over `hash` as the pseudorandom function
+/// Derives `len` bytes from `password` and `salt`
+pub fn pbkdf2(hash: HashT, password: &[u8], salt: &[u8], iterations: uint, len: uint) -> ~[u8] {
+  let mut mac = Hmac::new(hash, password);
+  let mut out = slice::with_capacity(len);
+  let mut counter = 1u32;
+
+  while out.len() < len {
+    // T_i = U_1 ^ U_2 ^ ... ^ U_c, with U_1 = PRF(P, S || INT(i)) and U_j = PRF(P, U_{j-1})
+    mac.input(salt);
+    mac.input([(counter >> 24) as u8, (counter >> 16) as u8, (counter >> 8) as u8, counter as u8]);
+    let mut u = mac.result();
+    let mut t = u.clone();
+    for _ in range(1, iterations) {
+      mac.input(u);
+      u = mac.result();
+      for (a, &b) in t.mut_iter().zip(u.iter()) { *a ^= b; }
+    }
+
+    let take = min(t.len(), len - out.len());
+    out.push_all(t.slice_to(take));
+    counter += 1;
+  }
+  out
+}
+
+/// KDF2 from ISO 18033-2, the same as the ANSI X9.63 KDF, as used by RSA-KEM
+/// Derives `len` bytes by hashing `z`, a counter starting at 1 and `other_info`
+pub fn kdf2(hash: HashT, z: &[u8], other_info: &[u8], len: uint) -> ~[u8] {
+  let mut digest = hash.digest();
+  let mut out = slice::with_capacity(len);
+  let mut counter = 1u32;
+
+  while out.len() < len {
+    digest.input(z);
+    digest.input([(counter >> 24) as u8, (counter >> 16) as u8, (counter >> 8) as u8, counter as u8]);
+    digest.input(other_info);
+    let block = digest.result();
+    let take = min(block.len(), len - out.len());
+    out.push_all(block.slice_to(take));
+    counter += 1;
+  }
+  out
+}
+
+// Core of bcrypt_pbkdf: encrypts a fixed string 64 times under an EksBlowfish
+// state keyed with the SHA-512 hashes of the password and salt
+fn bcrypt_hash(sha2pass: &[u8], sha2salt: &[u8]) -> ~[u8] {
+  let mut state = Blowfish::init_state();
+  state.expand_key_with_salt(sha2pass, sha2salt);
+  for _ in range(0, 64) {
+    state.expand_key(sha2salt);
+    state.expand_key(sha2pass);
+  }
+
+  let magic = bytes!("OxychromaticBlowfishSwatDynamite");
+  let mut data = [0u32, ..8];
+  for i in range(0u, 8) {
+    data[i] = ((magic[4 * i] as u32) << 24) | ((magic[4 * i + 1] as u32) << 16) |
+              ((magic[4 * i + 2] as u32) << 8) | (magic[4 * i + 3] as u32);
+  }
+  for _ in range(0, 64) {
+    for i in range_step(0u, 8, 2) {
+      let (l, r) = state.encrypt_words(data[i], data[i + 1]);
+      data[i] = l;
+      data[i + 1] = r;
+    }
+  }
+
+  // Unlike the input, the output words are little-endian
+  let mut out = slice::with_capacity(32);
+  for &word in data.iter() {
+    out.push(word as u8);
+    out.push((word >> 8) as u8);
+    out.push((word >> 16) as u8);
+    out.push((word >> 24) as u8);
+  }
+  out
+}
+
+/// bcrypt_pbkdf from OpenBSD, used to encrypt OpenSSH private keys
+/// Derives `len` bytes from `password` and `salt` with `rounds` rounds, which
+/// must be at least 1
+pub fn bcrypt_pbkdf(password: &[u8], salt: &[u8], rounds: uint, len: uint) -> ~[u8] {
+  assert!(rounds > 0);
+  let stride = (len + 31) / 32;
+  let amt = (len + stride - 1) / stride;
+  let sha2pass = SHA512.hash(password);
+
+  let mut key = slice::from_elem(len, 0u8);
+  let mut remaining = len;
+  let mut count = 1u32;
+  while remaining > 0 {
+    let mut count_salt = salt.to_owned();
+    count_salt.push_all([(count >> 24) as u8, (count >> 16) as u8, (count >> 8) as u8, count as u8]);
+    let mut tmp = bcrypt_hash(sha2pass, SHA512.hash(count_salt));
+    let mut out = tmp.clone();
+    for _ in range(1, rounds) {
+      tmp = bcrypt_hash(sha2pass, SHA512.hash(tmp));
+      for (a, &b) in out.mut_iter().zip(tmp.iter()) { *a ^= b; }
+    }
+
+    // Output bytes are spread across the key, so every block contributes to all of it
+    let mut written = 0;
+    for i in range(0, min(amt, remaining)) {
+      let dest = i * stride + (count - 1) as uint;
+      if dest >= len { break }
+      key[dest] = out[i];
+      written += 1;
+    }
+    remaining -= written;
+    count += 1;
+  }
+  key
+}
+
+#[cfg(test)]
+mod test_kdf {
+  use super::{pbkdf2, kdf2, bcrypt_pbkdf};
+  use hash::{SHA1, SHA256};
+  use serialize::hex::{ToHex, FromHex};
+
+  // Vectors from RFC 6070
+  #[test]
+  fn test_pbkdf2_sha1() {
+    assert_eq!(pbkdf2(SHA1, bytes!("password"), bytes!("salt"), 1, 20).to_hex(),
+               ~"0c60c80f961f0e71f3a9b524af6012062fe037a6");
+    assert_eq!(pbkdf2(SHA1, bytes!("password"), bytes!("salt"), 2, 20).to_hex(),
+               ~"ea6c014dc72d6f8ccd1ed92ace1d41f0d8de8957");
+    assert_eq!(pbkdf2(SHA1, bytes!("password"), bytes!("salt"), 4096, 20).to_hex(),
+               ~"4b007901b765489abead49d926f721d065a429c1");
+    assert_eq!(pbkdf2(SHA1, bytes!("passwordPASSWORDpassword"),
+                      bytes!("saltSALTsaltSALTsaltSALTsaltSALTsalt"), 4096, 25).to_hex(),
+               ~"3d2eec4fe41c849b80c8d83662c0e44a8b291a964cf2f07038");
+  }
+
+  #[test]
+  fn test_pbkdf2_sha256() {
+    assert_eq!(pbkdf2(SHA256, bytes!("password"), bytes!("salt"), 1, 32).to_hex(),
+               ~"120fb6cffcf8b32c43e7225256c4f837a86548c92ccc35480805987cb70be17b");
+    assert_eq!(pbkdf2(SHA256, bytes!("password"), bytes!("salt"), 4096, 32).to_hex(),
+               ~"c5e478d59288c841aa530db6845c4c8d962893a001ce4e11a4963873aa98134a");
+  }
+
+  // Checked against the X9.63 KDF in Python's cryptography package
+  #[test]
+  fn test_kdf2() {
+    assert_eq!(kdf2(SHA256, bytes!("shared secret"), bytes!("other"), 48).to_hex(),
+               ~"38f5a7612c94e619368fb8e7282f2280859d806f75627dfec13b27a687581822\
+                 c894d8a21a25b9f565876ad2d7a02c1c");
+    assert_eq!(kdf2(SHA1, "96c05619d56c328ab95fe84b18264b08725b85e33fd34f08".from_hex().unwrap(), [], 16).to_hex(),
+               ~"00acefcdf40d167b2e0a7bcd464f3efb");
+  }
+
+  #[test]
+  fn test_bcrypt_pbkdf() {
+    assert_eq!(bcrypt_pbkdf(bytes!("password"), bytes!("salt"), 4, 32).to_hex(),
+               ~"5bbf0cc293587f1c3635555c27796598d47e579071bf427e9d8fbe842aba34d9");
+  }
+}
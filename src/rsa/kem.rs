@@ -0,0 +1,117 @@
+use rand::OSRng;
+use bignum::{BigUint, RandBigInt};
+use hash::{HashT, SHA256};
+use kdf::kdf2;
+use gcm::{Gcm, IV_LEN, TAG_LEN};
+use super::{PublicKey, PrivateKey, RsaResult, InvalidCiphertext, i2osp, os2ip, modulus_len};
+
+// `seal` uses AES-256-GCM with the key and IV both derived from the RSA-KEM secret
+static SEAL_KEY_LEN: uint = 32;
+
+impl PublicKey {
+  /// RSA-KEM from RFC 5990: encrypts a random integer below n and derives
+  /// `key_len` bytes from it with KDF2 over `hash`
+  /// Returns the encapsulated secret, which is as long as the modulus, and the key
+  pub fn kem_encapsulate(&self, hash: HashT, key_len: uint) -> RsaResult<(~[u8], ~[u8])> {
+    let z = OSRng::new().gen_biguint_below(&self.n);
+    self.kem_encapsulate_secret(&z, hash, key_len)
+  }
+
+  fn kem_encapsulate_secret(&self, z: &BigUint, hash: HashT,
+                            key_len: uint) -> RsaResult<(~[u8], ~[u8])> {
+    let k = modulus_len(&self.n);
+    let c = try!(i2osp(&try!(self.encrypt_biguint(z)), k));
+    let key = kdf2(hash, try!(i2osp(z, k)), [], key_len);
+    Ok((c, key))
+  }
+
+  /// Encrypt a message of any length with RSA-KEM and AES-256-GCM
+  /// The output is the encapsulated secret followed by the AES-GCM ciphertext
+  /// and tag, so it is 16 bytes plus the modulus length longer than `m`
+  pub fn seal(&self, m: &[u8]) -> RsaResult<~[u8]> {
+    let (mut out, key) = try!(self.kem_encapsulate(SHA256, SEAL_KEY_LEN + IV_LEN));
+    // Every message has a fresh key, so deriving the IV with it is safe
+    let gcm = Gcm::new(key.slice_to(SEAL_KEY_LEN)).unwrap();
+    let (ciphertext, tag) = gcm.encrypt(key.slice_from(SEAL_KEY_LEN), [], m);
+    out.push_all(ciphertext);
+    out.push_all(tag);
+    Ok(out)
+  }
+}
+
+impl PrivateKey {
+  /// The key derived from an RSA-KEM encapsulated secret by `kem_encapsulate`
+  pub fn kem_decapsulate(&self, c: &[u8], hash: HashT, key_len: uint) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    if c.len() != k { return Err(InvalidCiphertext) }
+    let z = try!(self.decrypt_biguint(&os2ip(c)));
+    Ok(kdf2(hash, try!(i2osp(&z, k)), [], key_len))
+  }
+
+  /// Decrypt a message encrypted by `seal`
+  /// Fails with `InvalidCiphertext` if it has been truncated or tampered with
+  pub fn open(&self, c: &[u8]) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    if c.len() < k + TAG_LEN { return Err(InvalidCiphertext) }
+    let key = try!(self.kem_decapsulate(c.slice_to(k), SHA256, SEAL_KEY_LEN + IV_LEN));
+    let gcm = Gcm::new(key.slice_to(SEAL_KEY_LEN)).unwrap();
+    let tag_start = c.len() - TAG_LEN;
+    match gcm.decrypt(key.slice_from(SEAL_KEY_LEN), [], c.slice(k, tag_start), c.slice_from(tag_start)) {
+      Some(m) => Ok(m),
+      None    => Err(InvalidCiphertext)
+    }
+  }
+}
+
+#[cfg(test)]
+mod test_kem {
+  use std::slice;
+  use super::super::{PrivateKey, InvalidCiphertext, os2ip};
+  use test_keys::PKCS1_PRIVATE_PEM;
+  use hash::SHA256;
+  use serialize::hex::{ToHex, FromHex};
+
+  // A fixed secret, with the encapsulation and key computed by Python
+  static KEM_CIPHERTEXT: &'static str =
+    "a4ee55bf3635c7893d60899944d2818f9efe334dc767d3167626b8ec317b23cc2e942334ed89018994525fa55ecbe523\
+     9f06f13b11224d2090bf8b4405919f5ff2f7d9fb3d1662a15bc7a904a3e1edef932f51af2e12c7e0601d2b9e3cafe515\
+     251813a0e91895199c1c26889549b615262c9aae132d06c8d91f7d694e51ed7e";
+  static KEM_KEY: &'static str = "947e14dcdb92afd8359233a684cadb16b68350eb9d89347125c57dcd32391d259be19c674f6b23dbebb66904";
+
+  #[test]
+  fn test_kem() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let public = private.public_key();
+    let z = os2ip("0148fbabc53ae495a04a02721cd298cddafde2755577b104".from_hex().unwrap());
+    let (c, key) = public.kem_encapsulate_secret(&z, SHA256, 44).unwrap();
+    assert_eq!(c.to_hex(), KEM_CIPHERTEXT.to_owned());
+    assert_eq!(key.to_hex(), KEM_KEY.to_owned());
+    assert_eq!(private.kem_decapsulate(KEM_CIPHERTEXT.from_hex().unwrap(), SHA256, 44).unwrap().to_hex(),
+               KEM_KEY.to_owned());
+
+    let (c, key) = public.kem_encapsulate(SHA256, 16).unwrap();
+    assert_eq!(c.len(), 128);
+    assert_eq!(private.kem_decapsulate(c, SHA256, 16).unwrap(), key);
+    assert_eq!(private.kem_decapsulate(c.slice_from(1), SHA256, 16).err(), Some(InvalidCiphertext));
+  }
+
+  #[test]
+  fn test_seal() {
+    let private = PrivateKey::from_pem(PKCS1_PRIVATE_PEM).unwrap();
+    let public = private.public_key();
+    // Far longer than the modulus
+    let message = slice::from_fn(10000, |i| i as u8);
+    let sealed = public.seal(message).unwrap();
+    assert_eq!(sealed.len(), 128 + 10000 + 16);
+    assert_eq!(private.open(sealed).unwrap(), message);
+
+    let empty = public.seal([]).unwrap();
+    assert_eq!(private.open(empty).unwrap(), ~[]);
+
+    let mut tampered = sealed.clone();
+    tampered[500] ^= 1;
+    assert_eq!(private.open(tampered).err(), Some(InvalidCiphertext));
+    assert_eq!(private.open(sealed.slice_to(sealed.len() - 1)).err(), Some(InvalidCiphertext));
+    assert_eq!(private.open(sealed.slice_to(100)).err(), Some(InvalidCiphertext));
+  }
+}
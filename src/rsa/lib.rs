@@ -7,12 +7,62 @@ extern crate bignum;
 extern crate serialize;
 extern crate rand;
 extern crate num;
+extern crate collections;
 
-use std::num::ToStrRadix;
+use std::num::{Zero, One, ToStrRadix};
+use std::{str, slice};
 use bignum::{BigUint, ToBigUint};
 use serialize::hex::{ToHex, FromHex};
+use rand::{Rng, OSRng};
+use num::Integer;
+use hash::HashT;
 
 pub mod primes;
+pub mod padding;
+pub mod hash;
+pub mod der;
+pub mod pkcs8;
+pub mod pem;
+pub mod aes;
+pub mod hmac;
+pub mod kdf;
+pub mod pbes2;
+pub mod jwk;
+pub mod blowfish;
+pub mod openssh;
+pub mod jws;
+pub mod gcm;
+pub mod jwe;
+pub mod kem;
+pub mod stream;
+pub mod drbg;
+#[cfg(test)]
+mod test_keys;
+
+/// Errors returned by RSA operations
+#[deriving(Eq, Clone, Show)]
+pub enum RsaError {
+  /// The message is too long for the key size and padding scheme
+  MessageTooLong,
+  /// The ciphertext is out of range or doesn't decrypt to a validly padded message
+  InvalidCiphertext,
+  /// The signature doesn't match the message
+  InvalidSignature,
+  /// The input couldn't be decoded, e.g. malformed hex or a non UTF-8 plaintext
+  DecodingError,
+  /// The integer doesn't fit in the requested number of bytes
+  IntegerTooLarge,
+  /// The key is malformed or too small for the requested operation
+  InvalidKey,
+  /// An encrypted key couldn't be decrypted, most likely because the password is wrong
+  IncorrectPassword,
+  /// No suitable primes were found, which suggests a broken random number generator
+  KeyGenerationFailed,
+  /// The public exponent is even, smaller than 3 or too large for the key size
+  InvalidExponent
+}
+
+pub type RsaResult<T> = Result<T, RsaError>;
 
 pub enum KeySizeT {
   DefaultKeySize,
@@ -20,8 +70,51 @@ pub enum KeySizeT {
 }
 
 pub enum PublicExponentT {
+  /// 65537
   DefaultExponent,
-  Exponent(uint)
+  Exponent(uint),
+  /// An exponent too large for a `uint`
+  BigExponent(BigUint)
+}
+
+/// The modulus under which the private exponent d is the inverse of e
+pub enum PrivateExponentT {
+  /// lambda(n) = lcm(p - 1, q - 1), as PKCS#1 and FIPS 186 specify and OpenSSL
+  /// uses. Gives the smallest d, and is the default
+  CarmichaelLambda,
+  /// phi(n) = (p - 1)(q - 1)
+  EulerPhi
+}
+
+#[deriving(Clone)]
+pub enum PaddingT {
+  NoPadding,
+  PKCS1Padding,
+  /// OAEP with the given hash function and optional label
+  OAEPPadding(HashT, Option<~[u8]>)
+}
+
+#[deriving(Eq, Clone, Show)]
+pub enum SaltLengthT {
+  /// The hash length when signing, and whatever the signature used when verifying
+  AutoSaltLength,
+  SaltLength(uint)
+}
+
+/// Parameters for RSASSA-PSS signatures
+#[deriving(Eq, Clone, Show)]
+pub struct PSSParams {
+  hash: HashT,
+  mgf_hash: HashT,
+  salt_len: SaltLengthT
+}
+
+impl PSSParams {
+  /// PSS parameters using `hash` for both the message and MGF1, with a salt
+  /// as long as the hash output
+  pub fn new(hash: HashT) -> PSSParams {
+    PSSParams{ hash: hash, mgf_hash: hash, salt_len: SaltLength(hash.output_bytes()) }
+  }
 }
 
 #[deriving(Show)]
@@ -33,64 +126,366 @@ pub struct PublicKey {
 
 #[deriving(Show)]
 pub struct PrivateKey {
+  e: BigUint,
+  d: BigUint,
+  n: BigUint,
+  crt: Option<CRTParams>
+}
+
+/// The prime factors of the modulus along with the exponents and coefficient
+/// needed to decrypt using the Chinese Remainder Theorem
+#[deriving(Show, Clone)]
+pub struct CRTParams {
+  p: BigUint,
+  q: BigUint,
+  dp: BigUint,
+  dq: BigUint,
+  qinv: BigUint,
+  /// The primes after the first two in a multi-prime key
+  other_primes: ~[OtherPrimeInfo]
+}
+
+/// A prime factor after the first two, with its CRT exponent and coefficient,
+/// as in RFC 8017's OtherPrimeInfo
+#[deriving(Show, Clone, Eq)]
+pub struct OtherPrimeInfo {
+  r: BigUint,
+  /// d mod (r - 1)
   d: BigUint,
-  n: BigUint
+  /// The inverse modulo r of the product of the primes before it
+  t: BigUint
 }
 
 /// Generate RSA key-pair with default size and exponent.
-pub fn gen_keys_default() -> (PublicKey, PrivateKey) {
+pub fn gen_keys_default() -> RsaResult<(PublicKey, PrivateKey)> {
   gen_keys(DefaultKeySize, DefaultExponent)
 }
 
-/// Generate RSA key-pair with given size and exponent.
-pub fn gen_keys(key_size: KeySizeT, e: PublicExponentT) -> (PublicKey, PrivateKey) {
+/// Generate RSA key-pair with given size and exponent, using the operating
+/// system's random number generator and d computed modulo lambda(n).
+pub fn gen_keys(key_size: KeySizeT, e: PublicExponentT) -> RsaResult<(PublicKey, PrivateKey)> {
+  gen_keys_with_rng(&mut OSRng::new(), key_size, e, CarmichaelLambda)
+}
+
+/// Generate RSA key-pair with given size and exponent, drawing the primes
+/// from `rng`, which must be cryptographically secure, and computing d
+/// modulo lambda(n) or phi(n).
+pub fn gen_keys_with_rng<R: Rng>(rng: &mut R, key_size: KeySizeT, e: PublicExponentT,
+                                 d: PrivateExponentT) -> RsaResult<(PublicKey, PrivateKey)> {
+  gen_multi_prime_keys_with_rng(rng, key_size, e, 2, d)
+}
+
+/// Generate a multi-prime RSA key-pair from RFC 8017 with `count` primes of
+/// about the same size, using the operating system's random number generator.
+/// Decryption gets faster as the primes get smaller.
+pub fn gen_multi_prime_keys(key_size: KeySizeT, e: PublicExponentT,
+                            count: uint) -> RsaResult<(PublicKey, PrivateKey)> {
+  gen_multi_prime_keys_with_rng(&mut OSRng::new(), key_size, e, count, CarmichaelLambda)
+}
+
+/// Like `gen_multi_prime_keys`, drawing the primes from `rng` and computing d
+/// modulo lambda(n) or phi(n).
+pub fn gen_multi_prime_keys_with_rng<R: Rng>(rng: &mut R, key_size: KeySizeT, e: PublicExponentT,
+                                             count: uint, d: PrivateExponentT) -> RsaResult<(PublicKey, PrivateKey)> {
   let key_size = match key_size {
     KeySize(key_size) => key_size,
     _                 => 1024
   };
-  let prime_size = key_size / 2;
+  if count < 2 || key_size < 2 * count { return Err(InvalidKey) }
+  let e = try!(public_exponent(e, key_size));
 
-  let e = match e {
-    Exponent(e) => e,
-    _           => 3u
-  }.to_biguint().unwrap();
-
-  let p = primes::rsa_prime(prime_size, &e);
-  let q = primes::rsa_prime(prime_size, &e);
-  let n = p * q;
+  // Each prime gets an equal share of the bits the product still lacks, the
+  // first ones taking any remainder. The top two bits of every prime are set,
+  // so two of them always make exactly `key_size` bits, but more can fall a bit
+  // short, and then every prime is drawn again
   let one = 1u.to_biguint().unwrap();
-  let et = (p - one) * (q - one);
-  let d = primes::invmod(&e, &et).unwrap();
-  
-  let public_key = PublicKey{ e: e, n: n.clone(), key_size: key_size };
-  let private_key = PrivateKey{ d: d, n: n };
-  (public_key, private_key)
+  let mut factors = slice::with_capacity(count);
+  let mut n = one.clone();
+  while n.bits() != key_size {
+    factors.clear();
+    n = one.clone();
+    for i in range(0, count) {
+      let (bits, left) = (if i == 0 { 0 } else { n.bits() }, count - i);
+      let r = primes::rsa_prime_with_rng(rng, (key_size - bits + left - 1) / left, &e);
+      n = n * r;
+      factors.push(r);
+    }
+  }
+
+  let totient = factors.iter().fold(one.clone(), |acc, r| {
+    let r1 = r - one;
+    match d {
+      CarmichaelLambda => acc.lcm(&r1),
+      EulerPhi         => acc * r1
+    }
+  });
+  let d = match primes::invmod(&e, &totient) {
+    Some(d) => d,
+    None    => return Err(InvalidKey)
+  };
+
+  let private_key = try!(PrivateKey::from_multi_primes(factors, e, d));
+  Ok((private_key.public_key(), private_key))
+}
+
+/// Generate an RSA key-pair following FIPS 186-5 Appendix A.1.3, using the
+/// operating system's random number generator
+/// The modulus must be an even number of bits, at least 2048 (the default),
+/// and the exponent odd with 2^16 < e < 2^256 (65537 by default)
+pub fn gen_keys_fips186(key_size: KeySizeT, e: PublicExponentT) -> RsaResult<(PublicKey, PrivateKey)> {
+  gen_keys_fips186_with_rng(&mut OSRng::new(), key_size, e)
+}
+
+/// Like `gen_keys_fips186`, drawing the primes from `rng`, which must be an
+/// approved random bit generator such as `drbg::HmacDrbg` seeded from the system
+pub fn gen_keys_fips186_with_rng<R: Rng>(rng: &mut R, key_size: KeySizeT,
+                                         e: PublicExponentT) -> RsaResult<(PublicKey, PrivateKey)> {
+  let nlen = match key_size {
+    KeySize(key_size) => key_size,
+    _                 => 2048
+  };
+  if nlen < 2048 || nlen % 2 != 0 { return Err(InvalidKey) }
+  let e = try!(public_exponent(e, nlen));
+  let one: BigUint = One::one();
+  if e <= one << 16 || e.bits() > 256 { return Err(InvalidExponent) }
+
+  // d must be larger than 2^(nlen/2), so start again in the unlikely case it isn't
+  let min_d = one << (nlen / 2);
+  loop {
+    let p = try!(or_error(primes::fips186_prime(rng, nlen, &e, None), KeyGenerationFailed));
+    let q = try!(or_error(primes::fips186_prime(rng, nlen, &e, Some(&p)), KeyGenerationFailed));
+    let lambda = (p - one).lcm(&(q - one));
+    let d = try!(or_error(primes::invmod(&e, &lambda), KeyGenerationFailed));
+    if d <= min_d { continue }
+
+    let private_key = try!(PrivateKey::from_primes(p, q, e.clone(), d));
+    return Ok((private_key.public_key(), private_key))
+  }
+}
+
+// The exponent for a `key_size`-bit modulus, which must be odd, at least 3
+// and shorter than the modulus
+fn public_exponent(e: PublicExponentT, key_size: uint) -> RsaResult<BigUint> {
+  let e = match e {
+    DefaultExponent => 65537u.to_biguint().unwrap(),
+    Exponent(e)     => e.to_biguint().unwrap(),
+    BigExponent(e)  => e
+  };
+  if e.is_even() || e < 3u.to_biguint().unwrap() || e.bits() >= key_size { return Err(InvalidExponent) }
+  Ok(e)
 }
 
 impl PublicKey {
-  pub fn encrypt_biguint(&self, m: &BigUint) -> BigUint {
-    primes::mod_exp(m, &self.e, &self.n)
+  /// Public key with modulus `n` and public exponent `e`
+  pub fn new(n: BigUint, e: BigUint) -> PublicKey {
+    let key_size = n.bits();
+    PublicKey{ e: e, n: n, key_size: key_size }
+  }
+
+  pub fn encrypt_biguint(&self, m: &BigUint) -> RsaResult<BigUint> {
+    if *m >= self.n { return Err(MessageTooLong) }
+    Ok(primes::mod_exp(m, &self.e, &self.n))
   }
 
   /// Encrypt a message using this public key
-  pub fn encrypt(&self, m: ~str) -> ~str {
-    let max_len = self.key_size / 8;
-    assert!( m.char_len() < max_len,
-      "Message must be less than {} bytes for RSA with key size {}",
-      max_len, self.key_size);
+  pub fn encrypt(&self, m: ~str) -> RsaResult<~str> {
+    if m.char_len() >= self.key_size / 8 { return Err(MessageTooLong) }
+
+    self.encrypt_biguint(&from_plaintext(m)).map(|c| to_hex(&c))
+  }
+
+  /// Encrypt a message using this public key and the given padding scheme
+  pub fn encrypt_padded(&self, m: ~str, scheme: PaddingT) -> RsaResult<~str> {
+    match scheme {
+      NoPadding => self.encrypt(m),
+      _         => self.encrypt_bytes(m.as_bytes(), scheme).map(|c| c.to_hex())
+    }
+  }
+
+  /// Encrypt binary data using this public key and the given padding scheme
+  /// The ciphertext is always exactly as long as the modulus
+  pub fn encrypt_bytes(&self, m: &[u8], scheme: PaddingT) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    let em = match scheme {
+      NoPadding                => Some(m.to_owned()),
+      PKCS1Padding             => padding::pkcs1_pad(m, k),
+      OAEPPadding(hash, label) => padding::oaep_pad(m, k, hash, label.unwrap_or(~[]))
+    };
+
+    let em = try!(or_error(em, MessageTooLong));
+    i2osp(&try!(self.encrypt_biguint(&os2ip(em))), k)
+  }
+
+  /// Verify a RSASSA-PKCS1-v1_5 signature of a message with the given hash function
+  pub fn verify(&self, hash: HashT, m: &[u8], signature: &[u8]) -> RsaResult<()> {
+    let k = modulus_len(&self.n);
+    let em = try!(self.recover_signed_block(signature, k));
 
-    to_hex(&self.encrypt_biguint(&from_plaintext(m)))
+    match padding::pkcs1_sign_pad(m, k, hash) {
+      Some(expected) => if em == expected { Ok(()) } else { Err(InvalidSignature) },
+      None           => Err(InvalidKey)
+    }
+  }
+
+  /// Verify a RSASSA-PSS signature of a message with the given parameters
+  pub fn verify_pss(&self, params: &PSSParams, m: &[u8], signature: &[u8]) -> RsaResult<()> {
+    let em_bits = self.n.bits() - 1;
+    let em = try!(self.recover_signed_block(signature, (em_bits + 7) / 8));
+
+    let salt_len = match params.salt_len {
+      AutoSaltLength       => None,
+      SaltLength(salt_len) => Some(salt_len)
+    };
+    if padding::pss_verify(m, em, em_bits, params.hash, params.mgf_hash, salt_len) {
+      Ok(())
+    } else {
+      Err(InvalidSignature)
+    }
+  }
+
+  // Apply the public key to `signature`, giving the encoded block of `len` bytes
+  fn recover_signed_block(&self, signature: &[u8], len: uint) -> RsaResult<~[u8]> {
+    if signature.len() != modulus_len(&self.n) { return Err(InvalidSignature) }
+    let s = os2ip(signature);
+    if s >= self.n { return Err(InvalidSignature) }
+
+    let m = primes::mod_exp(&s, &self.e, &self.n);
+    i2osp(&m, len).map_err(|_| InvalidSignature)
   }
 }
 
 impl PrivateKey {
-  pub fn decrypt_biguint(&self, c: &BigUint) -> BigUint {
-    primes::mod_exp(c, &self.d, &self.n)
+  /// Private key with only the modulus and the public and private exponents
+  /// Operations on these keys can't use the Chinese Remainder Theorem
+  pub fn new(n: BigUint, e: BigUint, d: BigUint) -> PrivateKey {
+    PrivateKey{ e: e, d: d, n: n, crt: None }
+  }
+
+  /// Private key from the prime factors of the modulus and the exponents
+  /// Fails if `q` has no inverse modulo `p`
+  pub fn from_primes(p: BigUint, q: BigUint, e: BigUint, d: BigUint) -> RsaResult<PrivateKey> {
+    PrivateKey::from_multi_primes(~[p, q], e, d)
+  }
+
+  /// Private key from two or more prime factors of the modulus, in RFC 8017's
+  /// order r_1, r_2, ..., and the exponents
+  /// Fails if the primes aren't distinct
+  pub fn from_multi_primes(factors: ~[BigUint], e: BigUint, d: BigUint) -> RsaResult<PrivateKey> {
+    if factors.len() < 2 { return Err(InvalidKey) }
+    let one: BigUint = One::one();
+    let (p, q) = (factors[0].clone(), factors[1].clone());
+    let dp = d.modulus(&(p - one));
+    let dq = d.modulus(&(q - one));
+    let qinv = try!(or_error(primes::invmod(&q, &p), InvalidKey));
+
+    let mut n = p * q;
+    let mut other_primes = slice::with_capacity(factors.len() - 2);
+    for r in factors.slice_from(2).iter() {
+      let t = try!(or_error(primes::invmod(&n, r), InvalidKey));
+      other_primes.push(OtherPrimeInfo{ r: r.clone(), d: d.modulus(&(r - one)), t: t });
+      n = n * *r;
+    }
+
+    let crt = CRTParams{ p: p, q: q, dp: dp, dq: dq, qinv: qinv, other_primes: other_primes };
+    Ok(PrivateKey{ e: e, d: d, n: n, crt: Some(crt) })
+  }
+
+  /// The public half of this key
+  pub fn public_key(&self) -> PublicKey {
+    PublicKey::new(self.n.clone(), self.e.clone())
+  }
+
+  pub fn decrypt_biguint(&self, c: &BigUint) -> RsaResult<BigUint> {
+    if *c >= self.n { return Err(InvalidCiphertext) }
+
+    Ok(match self.crt {
+      // m = m2 + q * (qInv * (m1 - m2) mod p), with m1 and m2 computed mod p
+      // and q using exponents half the size of d
+      Some(ref crt) => {
+        let m1 = primes::mod_exp(&c.modulus(&crt.p), &crt.dp, &crt.p);
+        let m2 = primes::mod_exp(&c.modulus(&crt.q), &crt.dq, &crt.q);
+        let diff = (m1 + crt.p) - m2.modulus(&crt.p);
+        let h = (crt.qinv * diff).modulus(&crt.p);
+        let mut m = m2 + h * crt.q;
+
+        // Each further prime r lifts m from modulo the product R of the primes
+        // before it to modulo R * r: m = m + R * (t * (m_r - m) mod r)
+        let mut product = crt.p * crt.q;
+        for prime in crt.other_primes.iter() {
+          let mr = primes::mod_exp(&c.modulus(&prime.r), &prime.d, &prime.r);
+          let diff = (mr + prime.r) - m.modulus(&prime.r);
+          let h = (prime.t * diff).modulus(&prime.r);
+          m = m + product * h;
+          product = product * prime.r;
+        }
+        m
+      },
+      None          => primes::mod_exp(c, &self.d, &self.n)
+    })
   }
 
   /// Decrypt a message using this private key
-  pub fn decrypt(&self, m: ~str) -> ~str {
-    to_plaintext(&self.decrypt_biguint(&from_hex(m)))
+  pub fn decrypt(&self, m: ~str) -> RsaResult<~str> {
+    let c = try!(from_hex(m));
+    to_plaintext(&try!(self.decrypt_biguint(&c)))
+  }
+
+  /// Decrypt a message using this private key and the given padding scheme
+  pub fn decrypt_padded(&self, m: ~str, scheme: PaddingT) -> RsaResult<~str> {
+    match scheme {
+      NoPadding => self.decrypt(m),
+      _         => {
+        let k = modulus_len(&self.n);
+        let c = try!(i2osp(&try!(from_hex(m)), k).map_err(|_| InvalidCiphertext));
+        let m = try!(self.decrypt_bytes(c, scheme));
+        or_error(str::from_utf8_owned(m), DecodingError)
+      }
+    }
+  }
+
+  /// Decrypt binary data using this private key and the given padding scheme
+  /// The ciphertext must be exactly as long as the modulus. Without padding
+  /// the plaintext is also as long as the modulus, including leading zeros
+  pub fn decrypt_bytes(&self, c: &[u8], scheme: PaddingT) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    if c.len() != k { return Err(InvalidCiphertext) }
+    let em = try!(i2osp(&try!(self.decrypt_biguint(&os2ip(c))), k));
+
+    let m = match scheme {
+      NoPadding                => Some(em),
+      PKCS1Padding             => padding::pkcs1_unpad(em, k),
+      OAEPPadding(hash, label) => padding::oaep_unpad(em, k, hash, label.unwrap_or(~[]))
+    };
+    or_error(m, InvalidCiphertext)
+  }
+
+  /// Sign a message using RSASSA-PKCS1-v1_5 with the given hash function
+  pub fn sign(&self, hash: HashT, m: &[u8]) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    match padding::pkcs1_sign_pad(m, k, hash) {
+      Some(em) => self.sign_block(em, k),
+      None     => Err(InvalidKey)
+    }
+  }
+
+  /// Sign a message using RSASSA-PSS with the given parameters
+  pub fn sign_pss(&self, params: &PSSParams, m: &[u8]) -> RsaResult<~[u8]> {
+    let k = modulus_len(&self.n);
+    let em_bits = self.n.bits() - 1;
+    let salt_len = match params.salt_len {
+      AutoSaltLength       => params.hash.output_bytes(),
+      SaltLength(salt_len) => salt_len
+    };
+    match padding::pss_encode(m, em_bits, params.hash, params.mgf_hash, salt_len) {
+      Some(em) => self.sign_block(em, k),
+      None     => Err(InvalidKey)
+    }
+  }
+
+  // Apply the private key to the encoded block `em`, giving a `k` byte signature
+  fn sign_block(&self, em: &[u8], k: uint) -> RsaResult<~[u8]> {
+    let s = try!(self.decrypt_biguint(&os2ip(em)).map_err(|_| InvalidKey));
+    i2osp(&s, k)
   }
 }
 
@@ -100,65 +495,637 @@ fn to_hex(m: &BigUint) -> ~str {
   m.to_str_radix(16)
 }
 
-fn to_plaintext(m: &BigUint) -> ~str {
-  m.to_str_radix(16).from_hex().unwrap().into_ascii().into_str()
+fn to_plaintext(m: &BigUint) -> RsaResult<~str> {
+  let bytes = try!(i2osp(m, (m.bits() + 7) / 8));
+  or_error(str::from_utf8_owned(bytes), DecodingError)
 }
 
 fn from_plaintext(m: ~str) -> BigUint {
-  BigUint::from_str_radix(m.as_bytes().to_hex(), 16).unwrap()
+  os2ip(m.as_bytes())
 }
 
-fn from_hex(m: ~str) -> BigUint {
-  BigUint::from_str_radix(m, 16).unwrap()
+fn from_hex(m: ~str) -> RsaResult<BigUint> {
+  or_error(BigUint::from_str_radix(m, 16), DecodingError)
+}
+
+// Turn a missing value into the error `err`
+fn or_error<T>(value: Option<T>, err: RsaError) -> RsaResult<T> {
+  match value {
+    Some(value) => Ok(value),
+    None        => Err(err)
+  }
+}
+
+// Length of the modulus `n` in bytes
+fn modulus_len(n: &BigUint) -> uint {
+  (n.bits() + 7) / 8
+}
+
+/// I2OSP from RFC 8017: the big-endian bytes of `m`, left-padded with zeros
+/// to exactly `len` bytes
+pub fn i2osp(m: &BigUint, len: uint) -> RsaResult<~[u8]> {
+  let mut hex = m.to_str_radix(16);
+  if hex.len() % 2 == 1 { hex = format!("0{}", hex); }
+  let bytes = hex.from_hex().unwrap();
+
+  // Leading zero bytes can be dropped, but nothing else
+  let start = bytes.iter().position(|&byte| byte != 0).unwrap_or(bytes.len());
+  let significant = bytes.slice_from(start);
+  if significant.len() > len { return Err(IntegerTooLarge) }
+
+  let mut padded = slice::from_elem(len - significant.len(), 0u8);
+  padded.push_all(significant);
+  Ok(padded)
+}
+
+/// OS2IP from RFC 8017: the integer represented by the big-endian bytes `m`
+pub fn os2ip(m: &[u8]) -> BigUint {
+  if m.is_empty() { return Zero::zero() }
+  BigUint::from_str_radix(m.to_hex(), 16).unwrap()
 }
 
 #[cfg(test)]
 mod test_rsa {
-  use super::{Exponent, KeySize, gen_keys_default, gen_keys,
-              from_hex, to_hex, from_plaintext, to_plaintext};
+  use super::{Exponent, KeySize, NoPadding, PKCS1Padding, OAEPPadding, PublicKey, PrivateKey,
+              PSSParams, AutoSaltLength, SaltLength, gen_keys_default, gen_keys, gen_keys_with_rng,
+              CarmichaelLambda, EulerPhi, gen_multi_prime_keys, gen_multi_prime_keys_with_rng,
+              gen_keys_fips186, gen_keys_fips186_with_rng, DefaultKeySize, DefaultExponent, BigExponent,
+              InvalidExponent,
+              MessageTooLong, InvalidCiphertext, InvalidSignature, DecodingError, InvalidKey,
+              IntegerTooLarge, from_hex, to_hex, from_plaintext, to_plaintext, i2osp, os2ip};
+  use hash::{SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256};
+  use drbg::HmacDrbg;
   use bignum::ToBigUint;
+  use serialize::hex::FromHex;
   use std::{str,slice};
 
+  // A 1024-bit key generated by OpenSSL, used to check interoperability
+  static OPENSSL_N: &'static str =
+    "ae36ca6d0203a4524ee1f7649bd628e19256ae338011fa9af93b19b1ccc2b957\
+     ef05860c2ad0cb454f9d876d73a8b73d57ae51e0d6b875d66d268433457da6fa\
+     069b9983c4e2e747f5b513b28e09c7f654eb0fa5c046804d3f5d1733721e0c73\
+     0379f1cf57c424740715b781984d286a8724d12f41747382d11f6e6b309c65bf";
+  static OPENSSL_D: &'static str =
+    "fb111b495dcdc1d29cbc2abc63998ff105e4c5c892995680610e3b4971be6f3d\
+     baca4b14dddf6a12c95a4d01ffdcf5b701d5c74a3028462a6fc342626193643e\
+     2c77938dfe57f3d9ea0eb6ae76c1736848ec06eeed11b53e36baef0eb2756c3b\
+     573938c04b094784a54b2cae879091b1cab3e2f75f152f37beabcbf36749b21";
+
+  fn openssl_public_key() -> PublicKey {
+    PublicKey::new(from_hex(OPENSSL_N.to_owned()).unwrap(), 65537u.to_biguint().unwrap())
+  }
+
+  static OPENSSL_P: &'static str =
+    "e7279587a2c3e0a29d5e0821b3544fd66ad308af27585093a536391ff934b7e0\
+     6f0adf3ec6f62243f3c8bd7301ea1c2c504b95b021544a8476053f2b1e051d11";
+  static OPENSSL_Q: &'static str =
+    "c0f0700ad46ef26358510ef4c7d817b1138ab126f10d51772159073fd55c422e\
+     bce236cb5cb1aeb5dfa6706cba360ce31bba729625f23cfe9413f445b39f95cf";
+
+  fn openssl_private_key() -> PrivateKey {
+    let p = from_hex(OPENSSL_P.to_owned()).unwrap();
+    let q = from_hex(OPENSSL_Q.to_owned()).unwrap();
+    let d = from_hex(OPENSSL_D.to_owned()).unwrap();
+    PrivateKey::from_primes(p, q, 65537u.to_biguint().unwrap(), d).unwrap()
+  }
+
+  // The same key without its prime factors
+  fn openssl_non_crt_private_key() -> PrivateKey {
+    let n = from_hex(OPENSSL_N.to_owned()).unwrap();
+    let d = from_hex(OPENSSL_D.to_owned()).unwrap();
+    PrivateKey::new(n, 65537u.to_biguint().unwrap(), d)
+  }
+
   #[test]
   fn test_conversions() {
     assert_eq!(from_plaintext(~"abcd"), 1633837924u.to_biguint().unwrap()) 
-    assert_eq!(from_hex(~"61626364"), 1633837924u.to_biguint().unwrap()) 
-    assert_eq!(to_plaintext(&1633837924u.to_biguint().unwrap()), ~"abcd") 
+    assert_eq!(from_hex(~"61626364"), Ok(1633837924u.to_biguint().unwrap())) 
+    assert_eq!(to_plaintext(&1633837924u.to_biguint().unwrap()), Ok(~"abcd")) 
     assert_eq!(to_hex(&1633837924u.to_biguint().unwrap()), ~"61626364") 
   }
 
+  #[test]
+  fn test_byte_conversions() {
+    let m = 1633837924u.to_biguint().unwrap();
+    assert_eq!(os2ip(bytes!("abcd")), m);
+    assert_eq!(os2ip(&[0u8, 0u8, 0x61u8, 0x62u8, 0x63u8, 0x64u8]), m);
+    assert_eq!(os2ip(&[]), 0u.to_biguint().unwrap());
+    assert_eq!(i2osp(&m, 4), Ok(~[0x61u8, 0x62u8, 0x63u8, 0x64u8]));
+    assert_eq!(i2osp(&m, 6), Ok(~[0u8, 0u8, 0x61u8, 0x62u8, 0x63u8, 0x64u8]));
+    assert_eq!(i2osp(&0u.to_biguint().unwrap(), 2), Ok(~[0u8, 0u8]));
+    assert_eq!(i2osp(&0u.to_biguint().unwrap(), 0), Ok(~[]));
+    assert_eq!(i2osp(&m, 3), Err(IntegerTooLarge));
+  }
+
   #[test]
   fn test_encrypt_decrypt_biguint() {
-    let (public, private) = gen_keys_default();
+    let (public, private) = gen_keys_default().unwrap();
     let m = 1633837924u.to_biguint().unwrap();
-    let encrypted = public.encrypt_biguint(&m);
-    let decrypted = private.decrypt_biguint(&encrypted);
+    let encrypted = public.encrypt_biguint(&m).unwrap();
+    let decrypted = private.decrypt_biguint(&encrypted).unwrap();
     assert_eq!(m, decrypted);
   }
 
+  #[test]
+  fn test_private_key_from_primes() {
+    let private = openssl_private_key();
+    let crt = private.crt.clone().unwrap();
+    assert_eq!(private.n, from_hex(OPENSSL_N.to_owned()).unwrap());
+    assert_eq!(crt.dp, from_hex(~"a1262687d5c04b05f341cfd776c6eb95e70952bf91656f5f4f497b0fc053b171\
+                                  44d8c684647ec9aaa7f525a65d0a5233ce92b33b8430d8767a34f431e20edc91").unwrap());
+    assert_eq!(crt.dq, from_hex(~"52fcd864801d3c9ae898ed02f2968b123a629472388acef89bda6c58d38ffc53\
+                                  9821010a95d3435fad542cd5477f0a2a017a3fe13f6f233729402ff86f1b7c57").unwrap());
+    assert_eq!(crt.qinv, from_hex(~"dc7147379dc125d2dae276c111c3d14147ba632fd3d39b19c37d3cd566fce4fd\
+                                    f454ceafeaee3264435382522d0e3afdce0f31e64f47e673e58fec76b93c3736").unwrap());
+
+    // p and q must be coprime
+    let p = from_hex(OPENSSL_P.to_owned()).unwrap();
+    let d = from_hex(OPENSSL_D.to_owned()).unwrap();
+    let e = 65537u.to_biguint().unwrap();
+    assert_eq!(PrivateKey::from_primes(p.clone(), p.clone(), e, d).err(), Some(InvalidKey));
+  }
+
+  #[test]
+  fn test_decrypt_crt() {
+    let public = openssl_public_key();
+    let crt_private = openssl_private_key();
+    let private = openssl_non_crt_private_key();
+    for m in [0u, 1, 2, 1633837924].iter() {
+      let m = m.to_biguint().unwrap();
+      let c = public.encrypt_biguint(&m).unwrap();
+      assert_eq!(crt_private.decrypt_biguint(&c), Ok(m.clone()));
+      assert_eq!(private.decrypt_biguint(&c), Ok(m));
+    }
+
+    let n = from_hex(OPENSSL_N.to_owned()).unwrap();
+    let largest = n - 1u.to_biguint().unwrap();
+    let c = public.encrypt_biguint(&largest).unwrap();
+    assert_eq!(crt_private.decrypt_biguint(&c), Ok(largest.clone()));
+    assert_eq!(private.decrypt_biguint(&c), Ok(largest));
+  }
+
+  #[test]
+  fn test_sign_non_crt() {
+    let m = bytes!("super secret message");
+    let signature = openssl_private_key().sign(SHA256, m).unwrap();
+    assert_eq!(openssl_non_crt_private_key().sign(SHA256, m), signature);
+  }
+
   #[test]
   fn test_encrypt_decrypt_default() {
-    let (public, private) = gen_keys_default();
+    let (public, private) = gen_keys_default().unwrap();
     let m = ~"super secret message";
-    let encrypted = public.encrypt(m.clone());
+    let encrypted = public.encrypt(m.clone()).unwrap();
     let decrypted = private.decrypt(encrypted);
-    assert_eq!(m, decrypted);
+    assert_eq!(Ok(m), decrypted);
   }
 
   #[test]
   fn test_encrypt_decrypt_five() {
-    let (public, private) = gen_keys(KeySize(2048), Exponent(5u));
+    let (public, private) = gen_keys(KeySize(2048), Exponent(5u)).unwrap();
     let m = ~"super secret message";
-    let encrypted = public.encrypt(m.clone());
+    let encrypted = public.encrypt(m.clone()).unwrap();
     let decrypted = private.decrypt(encrypted);
-    assert_eq!(m, decrypted);
+    assert_eq!(Ok(m), decrypted);
+  }
+
+  // Keys from a seeded generator are reproducible. Checked against a Python
+  // port of the prime search and of HMAC_DRBG
+  #[test]
+  fn test_gen_keys_with_rng() {
+    let seed = bytes!("rsa key generation test seed....");
+    let (public, private) = gen_keys_with_rng(&mut HmacDrbg::new(SHA256, seed, [], []), KeySize(512),
+                                              Exponent(65537u), CarmichaelLambda).unwrap();
+    assert_eq!(public.n, from_hex(~"eca2169fd30280deee04b23ad09dc9940839282ed1d30636695ca6d7df03bebe\
+                                   1738f606abc431a9f3d86cb59c87456200d9dfc93e6118c20deaab98584f8707").unwrap());
+    assert_eq!(private.d, from_hex(~"75b2fba8dc665ade47aa8b5228d0cae27822a1e383d740385138d5fdfabddca1\
+                                    b080fb42c51586e2a04da0786f0416e3981ce8250a8e117bdf4fbf8d8fef9695").unwrap());
+    assert_eq!(private.crt.unwrap().p,
+               from_hex(~"f280c5151b7b2170adf1440fef1f7894962ceda8fa80bfc9d3f1a742f444d29d").unwrap());
+
+    // The same primes, with d computed modulo phi(n) instead
+    let (again, private) = gen_keys_with_rng(&mut HmacDrbg::new(SHA256, seed, [], []), KeySize(512),
+                                             Exponent(65537u), EulerPhi).unwrap();
+    assert_eq!(again.n, public.n);
+    assert_eq!(private.d, from_hex(~"ec0406f8c5e79b4dbeace46f911fafac7c3f35faecc0c35385e72969ea3fbbff\
+                                    c5f63c360977e9e8ae8ede25a63b29269a5f6aff49f7a52783bd64778b184a51").unwrap());
+    let m = bytes!("either exponent works");
+    assert_eq!(private.decrypt_bytes(again.encrypt_bytes(m, PKCS1Padding).unwrap(), PKCS1Padding),
+               Ok(m.to_owned()));
+  }
+
+  #[test]
+  fn test_gen_multi_prime_keys() {
+    for &(size, count) in [(1024u, 3u), (1025, 4)].iter() {
+      let (public, private) = gen_multi_prime_keys(KeySize(size), DefaultExponent, count).unwrap();
+      assert_eq!(public.n.bits(), size);
+      assert_eq!(private.crt.get_ref().other_primes.len(), count - 2);
+
+      let m = bytes!("multi-prime message");
+      assert_eq!(private.decrypt_bytes(public.encrypt_bytes(m, PKCS1Padding).unwrap(), PKCS1Padding),
+                 Ok(m.to_owned()));
+      let der = private.to_pkcs1_der().unwrap();
+      assert_eq!(PrivateKey::from_pkcs1_der(der).unwrap().d, private.d);
+      // OpenSSH keys only have room for two primes
+      assert_eq!(private.to_openssh("", None).err(), Some(InvalidKey));
+    }
+
+    assert_eq!(gen_multi_prime_keys(KeySize(1024), DefaultExponent, 1).err(), Some(InvalidKey));
+    let mut rng = HmacDrbg::new(SHA256, bytes!("rsa key generation test seed...."), [], []);
+    let (_, two_primes) = gen_multi_prime_keys_with_rng(&mut rng, KeySize(512), Exponent(65537u), 2,
+                                                        CarmichaelLambda).unwrap();
+    assert!(two_primes.crt.unwrap().other_primes.is_empty());
+
+    // Checked against a Python port of the prime generation
+    let seed = bytes!("rsa key generation test seed....");
+    let (public, private) = gen_multi_prime_keys_with_rng(&mut HmacDrbg::new(SHA256, seed, [], []), KeySize(1024),
+                                                          Exponent(65537u), 5, CarmichaelLambda).unwrap();
+    assert_eq!(public.n, from_hex(~"91be5c94be517faa02e326c851acbc296175d8a214067a41cda094490eaef803\
+                                   22cb114a18733d098180fd5235790ea349fa0477046d214dfcf045f8f7dd54fb\
+                                   9beea33380d98ba4a814fd687db6214e87b5bb4a8e0c58d5e1a7e01626f1c546\
+                                   ee9287f8b75085918ca604d538f47bf385b8f44cb77188dd321b005a61ff3325").unwrap());
+    let crt = private.crt.unwrap();
+    assert_eq!(crt.p, from_hex(~"1e5018a2a36f642e15be2881fde3ef1292c59db51f5017f93b37").unwrap());
+    assert_eq!(crt.other_primes.len(), 3);
+  }
+
+  #[test]
+  fn test_gen_keys_default_exponent() {
+    let (public, private) = gen_keys_default().unwrap();
+    assert_eq!(public.e, 65537u.to_biguint().unwrap());
+    assert_eq!(private.e, 65537u.to_biguint().unwrap());
+  }
+
+  #[test]
+  fn test_gen_keys_invalid_exponent() {
+    for &e in [0u, 1, 2, 4, 65536].iter() {
+      assert_eq!(gen_keys(KeySize(512), Exponent(e)).err(), Some(InvalidExponent));
+    }
+    let one = 1u.to_biguint().unwrap();
+    assert_eq!(gen_keys(KeySize(512), BigExponent((one << 511) + one)).err(), Some(InvalidExponent));
+    assert_eq!(gen_keys(KeySize(512), BigExponent(one << 100)).err(), Some(InvalidExponent));
+  }
+
+  #[test]
+  fn test_gen_keys_big_exponent() {
+    let one = 1u.to_biguint().unwrap();
+    let e = (one << 100) + (one << 64) + one;
+    let (public, private) = gen_keys(KeySize(512), BigExponent(e.clone())).unwrap();
+    assert_eq!(public.e, e);
+    let m = bytes!("big exponent");
+    assert_eq!(private.decrypt_bytes(public.encrypt_bytes(m, PKCS1Padding).unwrap(), PKCS1Padding),
+               Ok(m.to_owned()));
+  }
+
+  #[test]
+  fn test_gen_keys_exact_size() {
+    for &size in [512u, 513, 1024].iter() {
+      let (public, private) = gen_keys(KeySize(size), Exponent(65537u)).unwrap();
+      assert_eq!(public.n.bits(), size);
+      assert_eq!(public.key_size, size);
+      assert_eq!(private.public_key().key_size, size);
+    }
+  }
+
+  // Checked against a Python port of FIPS 186-5 Appendix A.1.3 and of HMAC_DRBG
+  static FIPS186_N: &'static str =
+    "dc3fe8779e1638ea48a852712283acef3f8e14606111d64676bfd33a4d6ffb11\
+     ed3cf75f269c06031d17249ea4d15d0393319cbe119c321887773a6ef62f7ae8\
+     eaf1c2d86619938eb20e766e6d1128ee674cbd3643b87d630366b4ddb1c32b16\
+     e42a80fe91bac47947c1f767716ab86039158fde516abc9f007cecc09137a842\
+     9c229cd79ad7d2f30e691a07fc5727f879cf40186c5138db287b21aadf7f542e\
+     746d41eed28815e94b6040f5b548278d5ed4040b4384a8bbcc6ab2ce24dbf9df\
+     9fd5c9f61732f3538dbef0aae8c81ed934ab94bc9537bbd536e567cc09b6f1ce\
+     34e5e28ce53ab5af8ae02e37e19191da80f1625e780138b53745ac7cff811f77";
+  static FIPS186_D: &'static str =
+    "45d93eb030c27b6a51809024b0fd716aae8d3089203fb7e4c0e434e8d8861ae9\
+     a48321305bde3f49dd7fe96d2677ff379306b8dece56eacc3ca9e32e2d850145\
+     bcbccf3e31a30ad8c63f2feff546ebe42f7e76c32ed6e36cf3372f44df8b35af\
+     1ccd1dfb9ddbcd53691e6a87b3c05498af81d70e7a638f4e1da980abd162054a\
+     03ee8923ac51aeea2ca3d610c831e32ed372ea1cd123dd18322ef87dd41e2f52\
+     aebc99ece7042f1aceb259164dfbe9712c0ce12a0eae904245978b37d394f4b2\
+     85f7768f7ddaad43076e199e3ea0f74a7cc1daba1ede0ac9ec58a2fd91f05c8a\
+     475e7028f50d65a14c674e6b35d494623be8529b87efbe8428deaed5424e7f01";
+
+  #[test]
+  fn test_gen_keys_fips186_with_rng() {
+    let mut rng = HmacDrbg::new(SHA256, bytes!("rsa key generation test seed...."), [], []);
+    let (public, private) = gen_keys_fips186_with_rng(&mut rng, DefaultKeySize, DefaultExponent).unwrap();
+    assert_eq!(public.n, from_hex(FIPS186_N.to_owned()).unwrap());
+    assert_eq!(public.e, 65537u.to_biguint().unwrap());
+    assert_eq!(private.d, from_hex(FIPS186_D.to_owned()).unwrap());
+    assert_eq!(public.key_size, 2048);
+  }
+
+  #[test]
+  fn test_gen_keys_fips186() {
+    let (public, private) = gen_keys_fips186(KeySize(2048), Exponent(65537u)).unwrap();
+    let crt = private.crt.clone().unwrap();
+    let one = 1u.to_biguint().unwrap();
+    assert_eq!(public.n.bits(), 2048);
+    // p and q are both at least sqrt(2) * 2^1023, and far apart
+    assert!(crt.p * crt.p >= one << 2047 && crt.q * crt.q >= one << 2047);
+    assert!((if crt.p > crt.q { crt.p - crt.q } else { crt.q - crt.p }) > one << 924);
+    assert!(private.d > one << 1024);
+
+    let m = bytes!("fips message");
+    assert_eq!(private.decrypt_bytes(public.encrypt_bytes(m, PKCS1Padding).unwrap(), PKCS1Padding),
+               Ok(m.to_owned()));
+  }
+
+  #[test]
+  fn test_gen_keys_fips186_parameters() {
+    // Too small, odd sizes, and exponents out of range or even
+    assert_eq!(gen_keys_fips186(KeySize(1024), Exponent(65537u)).err(), Some(InvalidKey));
+    assert_eq!(gen_keys_fips186(KeySize(2049), Exponent(65537u)).err(), Some(InvalidKey));
+    assert_eq!(gen_keys_fips186(KeySize(2048), Exponent(3u)).err(), Some(InvalidExponent));
+    assert_eq!(gen_keys_fips186(KeySize(2048), Exponent(65536u)).err(), Some(InvalidExponent));
+    assert_eq!(gen_keys_fips186(KeySize(2048), Exponent(65538u)).err(), Some(InvalidExponent));
+    let one = 1u.to_biguint().unwrap();
+    assert_eq!(gen_keys_fips186(KeySize(2048), BigExponent((one << 256) + one)).err(), Some(InvalidExponent));
+  }
+
+  #[test]
+  fn test_encrypt_decrypt_pkcs1() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = ~"super secret message";
+    let encrypted = public.encrypt_padded(m.clone(), PKCS1Padding).unwrap();
+    let decrypted = private.decrypt_padded(encrypted, PKCS1Padding);
+    assert_eq!(Ok(m), decrypted);
+  }
+
+  #[test]
+  fn test_encrypt_pkcs1_randomized() {
+    let (public, _) = gen_keys_default().unwrap();
+    let m = ~"super secret message";
+    let first = public.encrypt_padded(m.clone(), PKCS1Padding).unwrap();
+    let second = public.encrypt_padded(m.clone(), PKCS1Padding).unwrap();
+    assert!(first != second);
+  }
+
+  #[test]
+  fn test_decrypt_pkcs1_malformed() {
+    let (public, private) = gen_keys_default().unwrap();
+    let encrypted = public.encrypt_padded(~"super secret message", NoPadding).unwrap();
+    assert_eq!(private.decrypt_padded(encrypted, PKCS1Padding), Err(InvalidCiphertext));
+  }
+
+  #[test]
+  fn test_encrypt_decrypt_bytes() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = ~[0x00u8, 0x00u8, 0xffu8, 0x00u8, 0x80u8, 0x0au8];
+    let schemes = [PKCS1Padding, OAEPPadding(SHA256, None)];
+    for scheme in schemes.iter() {
+      let encrypted = public.encrypt_bytes(m, scheme.clone()).unwrap();
+      assert_eq!(encrypted.len(), 128);
+      assert_eq!(private.decrypt_bytes(encrypted, scheme.clone()), Ok(m.clone()));
+    }
+
+    // Without padding the plaintext comes back at the full modulus length
+    let encrypted = public.encrypt_bytes(m, NoPadding).unwrap();
+    let decrypted = private.decrypt_bytes(encrypted, NoPadding).unwrap();
+    assert_eq!(decrypted.len(), 128);
+    assert_eq!(os2ip(decrypted), os2ip(m));
+  }
+
+  #[test]
+  fn test_decrypt_bytes_invalid() {
+    let public = openssl_public_key();
+    let private = openssl_private_key();
+    let encrypted = public.encrypt_bytes(bytes!("session key"), PKCS1Padding).unwrap();
+    assert_eq!(private.decrypt_bytes(encrypted.slice_from(1), PKCS1Padding),
+               Err(InvalidCiphertext));
+    let mut too_long = encrypted.clone();
+    too_long.push(0u8);
+    assert_eq!(private.decrypt_bytes(too_long, PKCS1Padding), Err(InvalidCiphertext));
+    assert_eq!(private.decrypt_bytes(&[0xffu8, ..128], PKCS1Padding), Err(InvalidCiphertext));
+    assert_eq!(public.encrypt_bytes(&[0x61u8, ..118], PKCS1Padding), Err(MessageTooLong));
+    assert_eq!(public.encrypt_bytes(&[0xffu8, ..128], NoPadding), Err(MessageTooLong));
+  }
+
+  #[test]
+  fn test_encrypt_decrypt_oaep() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = ~"super secret message";
+    for &hash in [SHA1, SHA256, SHA512].iter() {
+      let scheme = OAEPPadding(hash, Some(bytes!("label").to_owned()));
+      let encrypted = public.encrypt_padded(m.clone(), scheme.clone()).unwrap();
+      let decrypted = private.decrypt_padded(encrypted, scheme);
+      assert_eq!(Ok(m.clone()), decrypted);
+    }
+  }
+
+  #[test]
+  fn test_decrypt_oaep_malformed() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = ~"super secret message";
+    let encrypted = public.encrypt_padded(m.clone(), OAEPPadding(SHA256, None)).unwrap();
+    assert_eq!(private.decrypt_padded(encrypted.clone(), OAEPPadding(SHA1, None)),
+               Err(InvalidCiphertext));
+    assert_eq!(private.decrypt_padded(encrypted.clone(), PKCS1Padding), Err(InvalidCiphertext));
+    assert_eq!(private.decrypt_padded(encrypted,
+      OAEPPadding(SHA256, Some(bytes!("label").to_owned()))), Err(InvalidCiphertext));
+  }
+
+  #[test]
+  fn test_decrypt_oaep_openssl() {
+    let private = openssl_private_key();
+    let sha1_encrypted =
+      ~"1638062cbc5534f95d09a41da65a8c5ad60c3701db9141392be07394a551b62f\
+        f5e234688dae269eef31c402af19b0ddf4e8452e37749c48042a2b9946afe7a7\
+        71c18aca46617f7305d9470bc4f1b0a5445881b0eac0a15c2f19ef250c5629f4\
+        4c135c0885664daf4f29d057dab6186ce1de19ba571bf17f817cf60bab2d27fe";
+    assert_eq!(private.decrypt_padded(sha1_encrypted, OAEPPadding(SHA1, None)),
+               Ok(~"super secret message"));
+
+    let sha256_encrypted =
+      ~"89541b5e1683657faf94bd2664b8ff317fd533e638ccdc8ae1d5a0d802fa3a87\
+        f262a8803ac7188907de18a810ddd8f4b1d35c67ad7b66d4214c6dbe57ae608d\
+        d65010fc271cbc6263be23b1b2987fc30cbeb13b10c300d7e942e33da4fe82e1\
+        fd8ef41298323831dbc7163586e679941787498efe452b21da347c684c3208b4";
+    assert_eq!(private.decrypt_padded(sha256_encrypted,
+                 OAEPPadding(SHA256, Some(bytes!("rsa").to_owned()))),
+               Ok(~"super secret message"));
+  }
+
+  #[test]
+  fn test_sign_verify() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = bytes!("release artifact");
+    for &hash in [SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256].iter() {
+      let signature = private.sign(hash, m).unwrap();
+      assert_eq!(signature.len(), 128);
+      assert!(public.verify(hash, m, signature).is_ok());
+      assert_eq!(public.verify(hash, bytes!("tampered artifact"), signature), Err(InvalidSignature));
+    }
+  }
+
+  #[test]
+  fn test_verify_invalid() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = bytes!("release artifact");
+    let signature = private.sign(SHA256, m).unwrap();
+
+    // Wrong hash function
+    assert_eq!(public.verify(SHA1, m, signature), Err(InvalidSignature));
+
+    // Corrupted or truncated signatures
+    let mut bad = signature.clone();
+    bad[64] ^= 0x01;
+    assert_eq!(public.verify(SHA256, m, bad), Err(InvalidSignature));
+    assert_eq!(public.verify(SHA256, m, signature.slice_from(1)), Err(InvalidSignature));
+
+    // Wrong key
+    let (other_public, _) = gen_keys_default().unwrap();
+    assert_eq!(other_public.verify(SHA256, m, signature), Err(InvalidSignature));
+  }
+
+  #[test]
+  fn test_sign_verify_openssl() {
+    let public = openssl_public_key();
+    let private = openssl_private_key();
+    let m = bytes!("super secret message");
+    let signatures = [
+      (SHA1, "48aa0ed4952f3b6fe28fa5e4c9149d7cab55bb36b8e26b38c5e83fea37ae1033\
+              bb76de7665a0f7037bb8096da36ceb177b71ee3546060bd8c05bb611da23dbeb\
+              5683ce33e9029214e4156f9e149f7b48523dd4893d7833f8abaee3f8cf404d41\
+              333a55976f266f80008fb96ff615f172045115efb85981f78a6f6365377ede43"),
+      (SHA224, "3f561f851379ceea886a0b333706fd07c399e8f940fec13076abd8d6be095b11\
+                b9852a75332295bc47af78d02fc6075eded0b54c6d9ead293dc0cbd5c3f3189e\
+                901f4a07e74adc28ca6274c70baecb9beb82eaf626fc358f7e81c5677b2783fb\
+                3705c33da9a1f86523ebd66bd6b94d7f521b1e2df96008a87c75963d9760b6da"),
+      (SHA256, "25982d20f37c6088edda50788cff3dd809a2e1d2641138da8350de5be455acfc\
+                6b43eabc1fd39168bfc989093377c57e46fc7b37a9a7642da7bfe882c88a713d\
+                ebba4f3ff8b24f05eb54e53f6f828a78b541f47b24f6c544ce88fee7f5e93b32\
+                c9b8e3031b824dec091ea6bab7ee288a4d1ec89dff90d5696ab8d9577945629d"),
+      (SHA384, "4ccc5a33b792b0dba830c29d18b2120fa2765c516c1e340c5f4f9b9ace8a2162\
+                bf8c42df42d79aa613dc62b00fb948e46a1c8691f297330ddd33ba0b57a044d9\
+                f07e7ea950bc39f662734b4a604ba3be9b32a4e0245814bdb6a3b5945e269df5\
+                2fb444e5e815a6fa73f9321a81d69ef80c5cbc0c24c79baafcbc49936ff8432b"),
+      (SHA512, "7b86435f62d0461aa07d5d151029d112092403d580a96caec55bbeb116129624\
+                f7bb045d82c733006580506bc5e5107663a9a29946fbf6cd7aa00dab13c328af\
+                cc80e1634f055841a5d03c7b6248934ea341a23baccd4884148c2ea1b4e8631c\
+                542d28c08190287a2d56acf1e64821e81d1afbf3e465d1479efea78f83cf6c3f"),
+      (SHA512_256, "27ec10d74ffd4b0cc8eaba1e6653fc01b1aa5768a052537c78819a10620db86e\
+                    4351afa8a1b7bb21544c353c0302f23e59e735a5942b3c2e6423d01e04255d9b\
+                    ab81eee0a7b6003435758723acb7c5d046cb9091c2f52c8f1534f9c7eee3ccd6\
+                    daacd802a088c5d9e96cdded7ead41104eec4c496f6447fcd009e8248b0ca8ef")
+    ];
+
+    for &(hash, expected) in signatures.iter() {
+      let expected = expected.from_hex().unwrap();
+      assert_eq!(private.sign(hash, m), Ok(expected.clone()));
+      assert!(public.verify(hash, m, expected).is_ok());
+    }
+  }
+
+  #[test]
+  fn test_sign_verify_pss() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = bytes!("release artifact");
+    for &hash in [SHA1, SHA256, SHA384, SHA512].iter() {
+      let params = PSSParams::new(hash);
+      let signature = private.sign_pss(&params, m).unwrap();
+      assert_eq!(signature.len(), 128);
+      assert!(public.verify_pss(&params, m, signature).is_ok());
+      assert_eq!(public.verify_pss(&params, bytes!("tampered artifact"), signature),
+                 Err(InvalidSignature));
+      assert_eq!(public.verify(hash, m, signature), Err(InvalidSignature));
+    }
+  }
+
+  #[test]
+  fn test_sign_verify_pss_salt_length() {
+    let (public, private) = gen_keys_default().unwrap();
+    let m = bytes!("release artifact");
+    let params = PSSParams{ hash: SHA256, mgf_hash: SHA1, salt_len: SaltLength(0) };
+    let signature = private.sign_pss(&params, m).unwrap();
+    assert!(public.verify_pss(&params, m, signature).is_ok());
+
+    let auto = PSSParams{ hash: SHA256, mgf_hash: SHA1, salt_len: AutoSaltLength };
+    assert!(public.verify_pss(&auto, m, signature).is_ok());
+
+    let wrong = PSSParams{ hash: SHA256, mgf_hash: SHA1, salt_len: SaltLength(32) };
+    assert_eq!(public.verify_pss(&wrong, m, signature), Err(InvalidSignature));
+  }
+
+  #[test]
+  fn test_verify_pss_openssl() {
+    let public = openssl_public_key();
+    let m = bytes!("super secret message");
+
+    let signature =
+      "5e631d3a68344365ce02ce6c2f53e1afea92f3fac93be6ab71d2db42b6b1b89e\
+       3d51276e615a4765f07b43b84a7108d7a446f42d5340f8834ca48a614c304d8e\
+       aad6c7ac77b95e943cd377ebca177452abc1d448fda84c76f3c190824b8164d5\
+       bee23828d052bead22ff6756e595af4b512ecc4f0f07f0ee6cf77a0965680efd".from_hex().unwrap();
+    assert!(public.verify_pss(&PSSParams::new(SHA256), m, signature).is_ok());
+
+    // Signed with the maximum salt length for the key
+    let signature =
+      "131c1fcf29f2872ef6124497c76cdef3561a011a95a02688defeca12e46b3d0c\
+       8fc46a0e955d92ede43b357699517c42eabc2c3f49bcb6772efd2188b6fbb493\
+       1f37ae7fd1418cfa8068aac41e4c351d31ddfc1b16eb192fe22d2eacb499b886\
+       007f8bce93e11b5269affd650bb782b36c035cffbdb1f0f6b4a956f3a62fa08c".from_hex().unwrap();
+    let auto = PSSParams{ hash: SHA256, mgf_hash: SHA256, salt_len: AutoSaltLength };
+    assert!(public.verify_pss(&auto, m, signature).is_ok());
+    let max = PSSParams{ hash: SHA256, mgf_hash: SHA256, salt_len: SaltLength(94) };
+    assert!(public.verify_pss(&max, m, signature).is_ok());
+    assert_eq!(public.verify_pss(&PSSParams::new(SHA256), m, signature), Err(InvalidSignature));
+
+    // Signed with SHA-512, MGF1 with SHA-1 and no salt
+    let signature =
+      "534570f315d4ea95172727220000044053738c2687822525b2236d1ad663ae3f\
+       8f197c27c956262097d69efb91da43fb1f13f57bbd8e44aec35f33f9faab7d34\
+       c775ee67d3d6e4447b4b435584718924dc8909e6a8c87de2c959dd5d6c662de2\
+       b2c04b8dee97d6c9288aaf6207096ac7d38c1e92d38dc33b645b3d2bd1060bb9".from_hex().unwrap();
+    let params = PSSParams{ hash: SHA512, mgf_hash: SHA1, salt_len: SaltLength(0) };
+    assert!(public.verify_pss(&params, m, signature).is_ok());
+  }
+
+  #[test]
+  fn test_message_too_long_pkcs1() {
+    let (public, _) = gen_keys_default().unwrap();
+    let m = str::from_chars(slice::from_elem(118, 'a'));
+    assert_eq!(public.encrypt_padded(m, PKCS1Padding), Err(MessageTooLong));
   }
 
   #[test]
-  #[should_fail]
   fn test_message_too_long() {
-    let (public, _) = gen_keys_default();
+    let (public, _) = gen_keys_default().unwrap();
     let m = str::from_chars(slice::from_elem(128, 'a'));
-    public.encrypt(m.clone());
+    assert_eq!(public.encrypt(m.clone()), Err(MessageTooLong));
+  }
+
+  #[test]
+  fn test_message_out_of_range() {
+    let public = openssl_public_key();
+    let n = from_hex(OPENSSL_N.to_owned()).unwrap();
+    assert_eq!(public.encrypt_biguint(&n), Err(MessageTooLong));
+  }
+
+  #[test]
+  fn test_decrypt_malformed() {
+    let private = openssl_private_key();
+
+    // Not hex at all
+    assert_eq!(private.decrypt(~"not a ciphertext"), Err(DecodingError));
+    assert_eq!(private.decrypt_padded(~"not a ciphertext", PKCS1Padding), Err(DecodingError));
+
+    // Larger than the modulus
+    let too_big = format!("1{}", OPENSSL_N);
+    assert_eq!(private.decrypt(too_big.clone()), Err(InvalidCiphertext));
+    assert_eq!(private.decrypt_padded(too_big, OAEPPadding(SHA1, None)), Err(InvalidCiphertext));
+
+    // Decrypts to something that isn't UTF-8
+    let c = openssl_public_key().encrypt_biguint(&0xffu.to_biguint().unwrap()).unwrap();
+    assert_eq!(private.decrypt(to_hex(&c)), Err(DecodingError));
+  }
+
+  #[test]
+  fn test_sign_key_too_small() {
+    // A 512-bit key has no room for a SHA-512 PKCS#1 v1.5 signature
+    let (_, private) = gen_keys(KeySize(512), Exponent(3u)).unwrap();
+    assert_eq!(private.sign(SHA512, bytes!("release artifact")), Err(InvalidKey));
   }
 }
@@ -0,0 +1,519 @@
+use std::{slice, mem};
+use rand::{Rng, OSRng};
+use hash::{HashT, SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256};
+
+// Minimum number of bytes of padding string for EME-PKCS1-v1_5
+static PKCS1_MIN_PS_LEN: uint = 8;
+
+/// EME-PKCS1-v1_5 encoding of message `m` into a block of `k` bytes
+/// The block is laid out as 0x00 || 0x02 || PS || 0x00 || M, where PS
+/// is at least 8 random non-zero bytes
+pub fn pkcs1_pad(m: &[u8], k: uint) -> Option<~[u8]> {
+  if k < m.len() + PKCS1_MIN_PS_LEN + 3 { return None }
+
+  let mut rng = OSRng::new();
+  let mut em = slice::with_capacity(k);
+  em.push(0x00u8);
+  em.push(0x02u8);
+  for _ in range(0, k - m.len() - 3) {
+    // Padding bytes must be non-zero so the separator can be found
+    let mut byte = 0u8;
+    while byte == 0 { byte = rng.gen(); }
+    em.push(byte);
+  }
+  em.push(0x00u8);
+  em.push_all(m);
+  Some(em)
+}
+
+// 0xff if `byte` is zero and 0x00 otherwise, without branching
+fn ct_is_zero(byte: u8) -> u8 {
+  (((byte as u32) - 1) >> 8) as u8
+}
+
+// 0xff if `a` < `b` and 0x00 otherwise, without branching
+// Both must be below 2^(bits - 1), which lengths always are
+fn ct_lt(a: uint, b: uint) -> u8 {
+  (((a - b) >> (mem::size_of::<uint>() * 8 - 1)) as u8) * 0xff
+}
+
+// `a` if `mask` is 0xff and `b` if it is 0x00, without branching
+fn ct_select(mask: u8, a: uint, b: uint) -> uint {
+  let mask = 0u - ((mask & 1) as uint);
+  (a & mask) | (b & !mask)
+}
+
+/// EME-PKCS1-v1_5 decoding of the `k` byte block `em`
+/// Returns `None` if the block is not a valid encryption block, taking the
+/// same time whichever check failed so as not to be a padding oracle
+pub fn pkcs1_unpad(em: &[u8], k: uint) -> Option<~[u8]> {
+  if em.len() != k || k < PKCS1_MIN_PS_LEN + 3 { return None }
+
+  // Every byte is examined whatever its value, and the checks are combined
+  // into `good` rather than returning at the first failure
+  let mut good = ct_is_zero(em[0]) & ct_is_zero(em[1] ^ 0x02);
+  // 0xff until the zero byte separating the padding from the message is found
+  let mut looking = 0xffu8;
+  let mut index = 0u;
+  for (i, &byte) in em.iter().enumerate().skip(2) {
+    let separator = looking & ct_is_zero(byte);
+    index = ct_select(separator, i + 1, index);
+    looking &= !separator;
+  }
+  good &= !looking;
+  good &= !ct_lt(index, PKCS1_MIN_PS_LEN + 3);
+
+  if good == 0xff { Some(em.slice_from(index).to_owned()) } else { None }
+}
+
+/// MGF1 mask generation function from RFC 8017, producing `len` bytes of
+/// mask from `seed` using the hash function `hash`
+pub fn mgf1(hash: HashT, seed: &[u8], len: uint) -> ~[u8] {
+  let mut mask = slice::with_capacity(len + hash.output_bytes());
+  let mut counter = 0u32;
+  while mask.len() < len {
+    let mut digest = hash.digest();
+    digest.input(seed);
+    digest.input(&[(counter >> 24) as u8, (counter >> 16) as u8, (counter >> 8) as u8, counter as u8]);
+    mask.push_all(digest.result());
+    counter += 1;
+  }
+  mask.truncate(len);
+  mask
+}
+
+// XOR `mask` into `data` in place
+fn xor_in_place(data: &mut [u8], mask: &[u8]) {
+  for (byte, &m) in data.mut_iter().zip(mask.iter()) { *byte ^= m; }
+}
+
+/// EME-OAEP encoding of message `m` into a block of `k` bytes, using hash
+/// function `hash` for both the label hash and MGF1
+/// The block is laid out as 0x00 || maskedSeed || maskedDB
+pub fn oaep_pad(m: &[u8], k: uint, hash: HashT, label: &[u8]) -> Option<~[u8]> {
+  let mut seed = slice::from_elem(hash.output_bytes(), 0u8);
+  OSRng::new().fill_bytes(seed.as_mut_slice());
+  oaep_pad_with_seed(m, k, hash, label, seed)
+}
+
+fn oaep_pad_with_seed(m: &[u8], k: uint, hash: HashT, label: &[u8], seed: &[u8]) -> Option<~[u8]> {
+  let h_len = hash.output_bytes();
+  if k < m.len() + 2 * h_len + 2 { return None }
+
+  // DB = lHash || PS || 0x01 || M
+  let mut db = hash.hash(label);
+  db.grow(k - m.len() - 2 * h_len - 2, &0x00u8);
+  db.push(0x01u8);
+  db.push_all(m);
+
+  let mut masked_seed = seed.to_owned();
+  xor_in_place(db.as_mut_slice(), mgf1(hash, seed, k - h_len - 1));
+  xor_in_place(masked_seed.as_mut_slice(), mgf1(hash, db, h_len));
+
+  let mut em = slice::with_capacity(k);
+  em.push(0x00u8);
+  em.push_all(masked_seed);
+  em.push_all(db);
+  Some(em)
+}
+
+/// EME-OAEP decoding of the `k` byte block `em`
+/// Returns `None` if the block is not a valid encryption block, without
+/// revealing which check failed
+pub fn oaep_unpad(em: &[u8], k: uint, hash: HashT, label: &[u8]) -> Option<~[u8]> {
+  let h_len = hash.output_bytes();
+  if em.len() != k || k < 2 * h_len + 2 { return None }
+
+  let mut seed = em.slice(1, h_len + 1).to_owned();
+  let mut db = em.slice_from(h_len + 1).to_owned();
+  xor_in_place(seed.as_mut_slice(), mgf1(hash, db, h_len));
+  xor_in_place(db.as_mut_slice(), mgf1(hash, seed, k - h_len - 1));
+
+  // Accumulate every failure into `bad` so that all malformed blocks take
+  // the same path and produce the same error
+  let mut bad = em[0];
+  for (&a, &b) in db.iter().zip(hash.hash(label).iter()) { bad |= a ^ b; }
+
+  // Find the 0x01 separating the zero padding from the message, examining
+  // every byte whatever its value. `looking` is 0xff until it is found
+  let mut looking = 0xffu8;
+  let mut index = 0u;
+  for (i, &byte) in db.slice_from(h_len).iter().enumerate() {
+    let separator = looking & ct_is_zero(byte ^ 0x01);
+    // Anything but zeros before the separator is malformed
+    bad |= looking & !separator & !ct_is_zero(byte);
+    index = ct_select(separator, h_len + i + 1, index);
+    looking &= !separator;
+  }
+  bad |= looking;
+
+  if bad == 0 { Some(db.slice_from(index).to_owned()) } else { None }
+}
+
+// DER encoding of the DigestInfo AlgorithmIdentifier and the OCTET STRING
+// header for each hash function, which the digest itself is appended to
+static SHA1_DIGEST_INFO: [u8, ..15] = [
+  0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04, 0x14
+];
+static SHA224_DIGEST_INFO: [u8, ..19] = [
+  0x30, 0x2d, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04,
+  0x05, 0x00, 0x04, 0x1c
+];
+static SHA256_DIGEST_INFO: [u8, ..19] = [
+  0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01,
+  0x05, 0x00, 0x04, 0x20
+];
+static SHA384_DIGEST_INFO: [u8, ..19] = [
+  0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02,
+  0x05, 0x00, 0x04, 0x30
+];
+static SHA512_DIGEST_INFO: [u8, ..19] = [
+  0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03,
+  0x05, 0x00, 0x04, 0x40
+];
+static SHA512_256_DIGEST_INFO: [u8, ..19] = [
+  0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x06,
+  0x05, 0x00, 0x04, 0x20
+];
+
+fn digest_info_prefix(hash: HashT) -> &'static [u8] {
+  match hash {
+    SHA1       => SHA1_DIGEST_INFO.as_slice(),
+    SHA224     => SHA224_DIGEST_INFO.as_slice(),
+    SHA256     => SHA256_DIGEST_INFO.as_slice(),
+    SHA384     => SHA384_DIGEST_INFO.as_slice(),
+    SHA512     => SHA512_DIGEST_INFO.as_slice(),
+    SHA512_256 => SHA512_256_DIGEST_INFO.as_slice()
+  }
+}
+
+/// EMSA-PKCS1-v1_5 encoding of the message `m` into a block of `k` bytes
+/// The block is laid out as 0x00 || 0x01 || PS || 0x00 || T, where PS is
+/// 0xff bytes and T is the DER encoded DigestInfo of the hash of `m`
+pub fn pkcs1_sign_pad(m: &[u8], k: uint, hash: HashT) -> Option<~[u8]> {
+  let prefix = digest_info_prefix(hash);
+  let t_len = prefix.len() + hash.output_bytes();
+  if k < t_len + PKCS1_MIN_PS_LEN + 3 { return None }
+
+  let mut em = slice::with_capacity(k);
+  em.push(0x00u8);
+  em.push(0x01u8);
+  em.grow(k - t_len - 3, &0xffu8);
+  em.push(0x00u8);
+  em.push_all(prefix);
+  em.push_all(hash.hash(m));
+  Some(em)
+}
+
+/// EMSA-PSS encoding of the message `m` into a block of `em_bits` bits,
+/// with a random salt of `salt_len` bytes
+/// The block is laid out as maskedDB || H || 0xbc
+pub fn pss_encode(m: &[u8], em_bits: uint, hash: HashT, mgf_hash: HashT,
+                  salt_len: uint) -> Option<~[u8]> {
+  let mut salt = slice::from_elem(salt_len, 0u8);
+  OSRng::new().fill_bytes(salt.as_mut_slice());
+  pss_encode_with_salt(m, em_bits, hash, mgf_hash, salt)
+}
+
+// Hash of M' = 0x00 * 8 || mHash || salt
+fn pss_hash(m_hash: &[u8], hash: HashT, salt: &[u8]) -> ~[u8] {
+  let mut digest = hash.digest();
+  digest.input(&[0u8, ..8]);
+  digest.input(m_hash);
+  digest.input(salt);
+  digest.result()
+}
+
+// Mask off the bits of the leftmost byte that don't fit into `em_bits`
+fn pss_top_mask(em_bits: uint) -> u8 {
+  0xffu8 >> (8 * ((em_bits + 7) / 8) - em_bits)
+}
+
+fn pss_encode_with_salt(m: &[u8], em_bits: uint, hash: HashT, mgf_hash: HashT,
+                        salt: &[u8]) -> Option<~[u8]> {
+  let h_len = hash.output_bytes();
+  let em_len = (em_bits + 7) / 8;
+  if em_len < h_len + salt.len() + 2 { return None }
+
+  let h = pss_hash(hash.hash(m), hash, salt);
+
+  // DB = PS || 0x01 || salt
+  let mut db = slice::from_elem(em_len - salt.len() - h_len - 2, 0x00u8);
+  db.push(0x01u8);
+  db.push_all(salt);
+  xor_in_place(db.as_mut_slice(), mgf1(mgf_hash, h, em_len - h_len - 1));
+  db[0] &= pss_top_mask(em_bits);
+
+  let mut em = db;
+  em.push_all(h);
+  em.push(0xbcu8);
+  Some(em)
+}
+
+/// EMSA-PSS verification that `em` is a valid encoding of the message `m`
+/// If `salt_len` is `None` the salt length is recovered from the encoding
+pub fn pss_verify(m: &[u8], em: &[u8], em_bits: uint, hash: HashT, mgf_hash: HashT,
+                  salt_len: Option<uint>) -> bool {
+  let h_len = hash.output_bytes();
+  let em_len = (em_bits + 7) / 8;
+  if em.len() != em_len || em_len < h_len + salt_len.unwrap_or(0) + 2 { return false }
+  if em[em_len - 1] != 0xbc { return false }
+
+  let masked_db = em.slice_to(em_len - h_len - 1);
+  let h = em.slice(em_len - h_len - 1, em_len - 1);
+  if masked_db[0] & !pss_top_mask(em_bits) != 0 { return false }
+
+  let mut db = masked_db.to_owned();
+  xor_in_place(db.as_mut_slice(), mgf1(mgf_hash, h, em_len - h_len - 1));
+  db[0] &= pss_top_mask(em_bits);
+
+  // DB must be zero padding followed by 0x01 and then the salt
+  let separator = match db.iter().position(|&byte| byte != 0x00) {
+    Some(i) if db[i] == 0x01 => i,
+    _                        => return false
+  };
+  let salt = db.slice_from(separator + 1);
+  match salt_len {
+    Some(salt_len) if salt.len() != salt_len => return false,
+    _                                        => ()
+  }
+
+  pss_hash(hash.hash(m), hash, salt).as_slice() == h
+}
+
+#[cfg(test)]
+mod test_padding {
+  use super::{pkcs1_pad, pkcs1_unpad, mgf1, oaep_pad, oaep_pad_with_seed, oaep_unpad,
+              pkcs1_sign_pad, pss_encode, pss_encode_with_salt, pss_verify,
+              ct_is_zero, ct_lt, ct_select};
+  use hash::{SHA1, SHA256, SHA384, SHA512};
+  use serialize::hex::ToHex;
+  use std::slice;
+
+  #[test]
+  fn test_pkcs1_pad() {
+    let m = bytes!("abcd");
+    let em = pkcs1_pad(m, 32).unwrap();
+    assert_eq!(em.len(), 32);
+    assert_eq!(em[0], 0x00);
+    assert_eq!(em[1], 0x02);
+    assert!(em.slice(2, 27).iter().all(|&byte| byte != 0x00));
+    assert_eq!(em[27], 0x00);
+    assert_eq!(em.slice_from(28), m);
+  }
+
+  #[test]
+  fn test_pkcs1_pad_too_long() {
+    assert!(pkcs1_pad(&[0x61u8, ..22], 32).is_none());
+    assert!(pkcs1_pad(&[0x61u8, ..21], 32).is_some());
+  }
+
+  #[test]
+  fn test_pkcs1_unpad() {
+    let m = bytes!("super secret message");
+    let em = pkcs1_pad(m, 64).unwrap();
+    assert_eq!(pkcs1_unpad(em, 64).unwrap(), m.to_owned());
+  }
+
+  #[test]
+  fn test_pkcs1_unpad_malformed() {
+    let m = bytes!("super secret message");
+    let em = pkcs1_pad(m, 64).unwrap();
+
+    // Wrong length
+    assert!(pkcs1_unpad(em, 65).is_none());
+
+    // Wrong block type
+    let mut bad = em.clone();
+    bad[1] = 0x01;
+    assert!(pkcs1_unpad(bad, 64).is_none());
+
+    // Leading byte must be zero
+    let mut bad = em.clone();
+    bad[0] = 0x01;
+    assert!(pkcs1_unpad(bad, 64).is_none());
+
+    // Padding string too short
+    let mut bad = em.clone();
+    bad[5] = 0x00;
+    assert!(pkcs1_unpad(bad, 64).is_none());
+
+    // No separator at all
+    let mut bad = em.clone();
+    for byte in bad.mut_slice_from(2).mut_iter() { *byte = 0xff; }
+    assert!(pkcs1_unpad(bad, 64).is_none());
+  }
+
+  #[test]
+  fn test_constant_time_helpers() {
+    assert_eq!(ct_is_zero(0), 0xff);
+    assert!(range(1u, 256).all(|byte| ct_is_zero(byte as u8) == 0));
+    assert_eq!(ct_lt(10, 11), 0xff);
+    assert_eq!(ct_lt(11, 11), 0);
+    assert_eq!(ct_lt(12, 11), 0);
+    assert_eq!(ct_lt(0, 1 << 20), 0xff);
+    assert_eq!(ct_select(0xff, 3, 7), 3);
+    assert_eq!(ct_select(0, 3, 7), 7);
+  }
+
+  #[test]
+  fn test_pkcs1_unpad_empty_message() {
+    let em = pkcs1_pad([], 64).unwrap();
+    assert_eq!(pkcs1_unpad(em, 64).unwrap(), ~[]);
+    // Exactly the minimum of eight padding bytes
+    let m = slice::from_elem(53, 0x61u8);
+    let em = pkcs1_pad(m, 64).unwrap();
+    assert_eq!(pkcs1_unpad(em, 64).unwrap(), m);
+  }
+
+  #[test]
+  fn test_mgf1() {
+    assert_eq!(mgf1(SHA1, bytes!("foo"), 3).to_hex(), ~"1ac907");
+    assert_eq!(mgf1(SHA1, bytes!("foo"), 5).to_hex(), ~"1ac9075cd4");
+    assert_eq!(mgf1(SHA256, bytes!("bar"), 5).to_hex(), ~"382576a784");
+    assert_eq!(mgf1(SHA1, bytes!("bar"), 50).len(), 50);
+  }
+
+  #[test]
+  fn test_oaep_pad() {
+    let m = bytes!("abcd");
+    let seed = [0x5au8, ..20];
+    let em = oaep_pad_with_seed(m, 64, SHA1, [], seed).unwrap();
+    assert_eq!(em.len(), 64);
+    assert_eq!(em[0], 0x00);
+    assert_eq!(oaep_unpad(em, 64, SHA1, []).unwrap(), m.to_owned());
+
+    // Encoding is deterministic given the seed, and randomized otherwise
+    assert_eq!(oaep_pad_with_seed(m, 64, SHA1, [], seed).unwrap(), em);
+    assert!(oaep_pad(m, 64, SHA1, []).unwrap() != oaep_pad(m, 64, SHA1, []).unwrap());
+  }
+
+  #[test]
+  fn test_oaep_pad_too_long() {
+    assert!(oaep_pad(&[0x61u8, ..23], 64, SHA1, []).is_none());
+    assert!(oaep_pad(&[0x61u8, ..22], 64, SHA1, []).is_some());
+    assert!(oaep_pad(&[], 129, SHA512, []).is_none());
+  }
+
+  #[test]
+  fn test_oaep_unpad() {
+    let m = bytes!("super secret message");
+    for &hash in [SHA1, SHA256, SHA512].iter() {
+      let em = oaep_pad(m, 256, hash, bytes!("label")).unwrap();
+      assert_eq!(oaep_unpad(em, 256, hash, bytes!("label")).unwrap(), m.to_owned());
+    }
+
+    // Empty messages are allowed
+    let em = oaep_pad([], 128, SHA256, []).unwrap();
+    assert_eq!(oaep_unpad(em, 128, SHA256, []).unwrap(), ~[]);
+  }
+
+  #[test]
+  fn test_oaep_unpad_malformed() {
+    let m = bytes!("super secret message");
+    let em = oaep_pad(m, 128, SHA256, bytes!("label")).unwrap();
+
+    // Wrong label or hash
+    assert!(oaep_unpad(em, 128, SHA256, bytes!("other")).is_none());
+    assert!(oaep_unpad(em, 128, SHA1, bytes!("label")).is_none());
+
+    // Leading byte must be zero
+    let mut bad = em.clone();
+    bad[0] = 0x01;
+    assert!(oaep_unpad(bad, 128, SHA256, bytes!("label")).is_none());
+
+    // Any change to the masked data breaks the hash or separator
+    for i in range(1u, 128) {
+      let mut bad = em.clone();
+      bad[i] ^= 0x80;
+      assert!(oaep_unpad(bad, 128, SHA256, bytes!("label")).is_none());
+    }
+
+    // Wrong length
+    assert!(oaep_unpad(em.slice_from(1), 127, SHA256, bytes!("label")).is_none());
+    assert!(oaep_unpad(em, 127, SHA256, bytes!("label")).is_none());
+  }
+
+  #[test]
+  fn test_pkcs1_sign_pad() {
+    let em = pkcs1_sign_pad(bytes!("abc"), 64, SHA1).unwrap();
+    assert_eq!(em.len(), 64);
+    assert_eq!(em.slice_to(2), &[0x00u8, 0x01u8]);
+    assert!(em.slice(2, 28).iter().all(|&byte| byte == 0xff));
+    assert_eq!(em.slice_from(28).to_hex(),
+      ~"003021300906052b0e03021a05000414a9993e364706816aba3e25717850c26c9cd0d89d");
+
+    for &hash in [SHA256, SHA384, SHA512].iter() {
+      let em = pkcs1_sign_pad(bytes!("abc"), 128, hash).unwrap();
+      assert_eq!(em.slice_from(128 - hash.output_bytes()), hash.hash(bytes!("abc")).as_slice());
+    }
+  }
+
+  #[test]
+  fn test_pkcs1_sign_pad_too_short() {
+    // SHA-512 DigestInfo is 83 bytes, leaving too little room in 93 bytes
+    assert!(pkcs1_sign_pad(bytes!("abc"), 93, SHA512).is_none());
+    assert!(pkcs1_sign_pad(bytes!("abc"), 94, SHA512).is_some());
+  }
+
+  #[test]
+  fn test_pss_encode() {
+    let m = bytes!("abc");
+    let salt = [0x5au8, ..20];
+    let em = pss_encode_with_salt(m, 1023, SHA1, SHA1, salt).unwrap();
+    assert_eq!(em.len(), 128);
+    assert_eq!(em[127], 0xbc);
+    assert_eq!(em[0] & 0x80, 0x00);
+    assert!(pss_verify(m, em, 1023, SHA1, SHA1, Some(20)));
+
+    // Encoding is deterministic given the salt, and randomized otherwise
+    assert_eq!(pss_encode_with_salt(m, 1023, SHA1, SHA1, salt).unwrap(), em);
+    assert!(pss_encode(m, 1023, SHA1, SHA1, 20).unwrap() !=
+            pss_encode(m, 1023, SHA1, SHA1, 20).unwrap());
+
+    // Block sizes that aren't a whole number of bytes
+    let em = pss_encode(m, 1020, SHA256, SHA256, 32).unwrap();
+    assert_eq!(em[0] & 0xf0, 0x00);
+    assert!(pss_verify(m, em, 1020, SHA256, SHA256, Some(32)));
+    let em = pss_encode(m, 1024, SHA256, SHA256, 32).unwrap();
+    assert!(pss_verify(m, em, 1024, SHA256, SHA256, Some(32)));
+  }
+
+  #[test]
+  fn test_pss_encode_too_short() {
+    assert!(pss_encode(bytes!("abc"), 8 * 85, SHA512, SHA512, 20).is_none());
+    assert!(pss_encode(bytes!("abc"), 8 * 85 + 1, SHA512, SHA512, 20).is_some());
+  }
+
+  #[test]
+  fn test_pss_verify_salt_length() {
+    let m = bytes!("abc");
+    for &salt_len in [0u, 20, 32, 94].iter() {
+      let em = pss_encode(m, 1023, SHA256, SHA1, salt_len).unwrap();
+      assert!(pss_verify(m, em, 1023, SHA256, SHA1, Some(salt_len)));
+      assert!(pss_verify(m, em, 1023, SHA256, SHA1, None));
+      assert!(!pss_verify(m, em, 1023, SHA256, SHA1, Some(salt_len + 1)));
+    }
+  }
+
+  #[test]
+  fn test_pss_verify_invalid() {
+    let m = bytes!("abc");
+    let em = pss_encode(m, 1023, SHA256, SHA256, 32).unwrap();
+
+    // Wrong message, hash or MGF hash
+    assert!(!pss_verify(bytes!("abd"), em, 1023, SHA256, SHA256, None));
+    assert!(!pss_verify(m, em, 1023, SHA512, SHA256, None));
+    assert!(!pss_verify(m, em, 1023, SHA256, SHA1, None));
+
+    // Any corruption of the block
+    for i in range(0u, 128) {
+      let mut bad = em.clone();
+      bad[i] ^= 0x01;
+      assert!(!pss_verify(m, bad, 1023, SHA256, SHA256, None));
+    }
+
+    // Wrong length
+    assert!(!pss_verify(m, em.slice_from(1), 1023, SHA256, SHA256, None));
+  }
+}
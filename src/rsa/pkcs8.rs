@@ -0,0 +1,342 @@
+use super::{PublicKey, PrivateKey, PSSParams, AutoSaltLength, SaltLength, RsaResult,
+            DecodingError, InvalidKey};
+use der::{DerReader, TAG_OID, TAG_NULL, TAG_OCTET_STRING, encode_tlv, encode_integer,
+          encode_sequence, encode_bit_string, read_outer_sequence};
+use hash::{HashT, ALL_HASHES, SHA1, SHA224, SHA256, SHA384, SHA512, SHA512_256};
+use std::num::{Zero, One};
+use bignum::{BigUint, ToBigUint};
+
+/// DER content of the rsaEncryption object identifier, 1.2.840.113549.1.1.1
+static RSA_ENCRYPTION_OID: [u8, ..9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01];
+/// id-RSASSA-PSS, 1.2.840.113549.1.1.10
+static RSASSA_PSS_OID: [u8, ..9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0a];
+/// id-mgf1, 1.2.840.113549.1.1.8
+static MGF1_OID: [u8, ..9] = [0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x08];
+static SHA1_OID: [u8, ..5] = [0x2b, 0x0e, 0x03, 0x02, 0x1a];
+static SHA224_OID: [u8, ..9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x04];
+static SHA256_OID: [u8, ..9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01];
+static SHA384_OID: [u8, ..9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02];
+static SHA512_OID: [u8, ..9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03];
+static SHA512_256_OID: [u8, ..9] = [0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x06];
+
+// Tags of the explicitly tagged fields of RSASSA-PSS-params
+static PSS_HASH_TAG: u8 = 0xa0;
+static PSS_MGF_TAG: u8 = 0xa1;
+static PSS_SALT_LEN_TAG: u8 = 0xa2;
+static PSS_TRAILER_TAG: u8 = 0xa3;
+
+// Values RFC 8017 uses when a field of RSASSA-PSS-params is left out
+static PSS_DEFAULT_SALT_LEN: uint = 20;
+static PSS_TRAILER_FIELD_BC: uint = 1;
+
+/// Algorithm a key is marked for in SubjectPublicKeyInfo and PrivateKeyInfo
+#[deriving(Eq, Clone, Show)]
+pub enum KeyAlgorithmT {
+  /// rsaEncryption, for any operation
+  RSAEncryption,
+  /// id-RSASSA-PSS, for PSS signatures only, optionally restricted to the given parameters
+  RSASSAPSS(Option<PSSParams>)
+}
+
+fn hash_oid(hash: HashT) -> &'static [u8] {
+  match hash {
+    SHA1       => SHA1_OID.as_slice(),
+    SHA224     => SHA224_OID.as_slice(),
+    SHA256     => SHA256_OID.as_slice(),
+    SHA384     => SHA384_OID.as_slice(),
+    SHA512     => SHA512_OID.as_slice(),
+    SHA512_256 => SHA512_256_OID.as_slice()
+  }
+}
+
+// Hash AlgorithmIdentifier, with the NULL parameters OpenSSL writes
+fn encode_hash_algorithm(hash: HashT) -> ~[u8] {
+  encode_sequence([encode_tlv(TAG_OID, hash_oid(hash)), encode_tlv(TAG_NULL, [])])
+}
+
+// DER encoding of RSASSA-PSS-params, leaving out fields that have their default value
+fn encode_pss_params(params: &PSSParams) -> ~[u8] {
+  let salt_len = match params.salt_len {
+    SaltLength(len) => len,
+    AutoSaltLength  => params.hash.output_bytes()
+  };
+
+  let mut fields = ~[];
+  if params.hash != SHA1 {
+    fields.push(encode_tlv(PSS_HASH_TAG, encode_hash_algorithm(params.hash)));
+  }
+  if params.mgf_hash != SHA1 {
+    let mgf = encode_sequence([encode_tlv(TAG_OID, MGF1_OID), encode_hash_algorithm(params.mgf_hash)]);
+    fields.push(encode_tlv(PSS_MGF_TAG, mgf));
+  }
+  if salt_len != PSS_DEFAULT_SALT_LEN {
+    fields.push(encode_tlv(PSS_SALT_LEN_TAG, encode_integer(&salt_len.to_biguint().unwrap())));
+  }
+  encode_sequence(fields)
+}
+
+fn encode_algorithm(alg: &KeyAlgorithmT) -> ~[u8] {
+  match *alg {
+    RSAEncryption               => encode_sequence([encode_tlv(TAG_OID, RSA_ENCRYPTION_OID),
+                                                    encode_tlv(TAG_NULL, [])]),
+    RSASSAPSS(None)             => encode_sequence([encode_tlv(TAG_OID, RSASSA_PSS_OID)]),
+    RSASSAPSS(Some(ref params)) => encode_sequence([encode_tlv(TAG_OID, RSASSA_PSS_OID),
+                                                    encode_pss_params(params)])
+  }
+}
+
+// Reads optional NULL parameters, which must be the last thing in `alg`
+fn read_null_params(alg: &mut DerReader) -> RsaResult<()> {
+  // Some encoders leave out the NULL parameters
+  if !alg.is_empty() {
+    let params = try!(alg.read_tlv(TAG_NULL));
+    if !params.is_empty() { return Err(DecodingError) }
+  }
+  if alg.is_empty() { Ok(()) } else { Err(DecodingError) }
+}
+
+fn read_hash_algorithm(reader: &mut DerReader) -> RsaResult<HashT> {
+  let mut alg = try!(reader.read_sequence());
+  let oid = try!(alg.read_tlv(TAG_OID));
+  let hash = match ALL_HASHES.iter().find(|&hash| hash_oid(*hash) == oid) {
+    Some(hash) => *hash,
+    None       => return Err(InvalidKey)
+  };
+  try!(read_null_params(&mut alg));
+  Ok(hash)
+}
+
+// Reads the explicitly tagged field `tag` if it comes next
+fn read_tagged_field<'a>(reader: &mut DerReader<'a>, tag: u8) -> RsaResult<Option<DerReader<'a>>> {
+  if reader.peek_tag() != Some(tag) { return Ok(None) }
+  let content = try!(reader.read_tlv(tag));
+  Ok(Some(DerReader::new(content)))
+}
+
+fn read_pss_params(reader: &mut DerReader) -> RsaResult<PSSParams> {
+  let mut seq = try!(reader.read_sequence());
+  let mut params = PSSParams{ hash: SHA1, mgf_hash: SHA1, salt_len: SaltLength(PSS_DEFAULT_SALT_LEN) };
+
+  match try!(read_tagged_field(&mut seq, PSS_HASH_TAG)) {
+    Some(mut field) => {
+      params.hash = try!(read_hash_algorithm(&mut field));
+      if !field.is_empty() { return Err(DecodingError) }
+    },
+    None => {}
+  }
+  match try!(read_tagged_field(&mut seq, PSS_MGF_TAG)) {
+    Some(mut field) => {
+      let mut mgf = try!(field.read_sequence());
+      // MGF1 is the only mask generation function
+      if try!(mgf.read_tlv(TAG_OID)) != MGF1_OID.as_slice() { return Err(InvalidKey) }
+      params.mgf_hash = try!(read_hash_algorithm(&mut mgf));
+      if !mgf.is_empty() || !field.is_empty() { return Err(DecodingError) }
+    },
+    None => {}
+  }
+  match try!(read_tagged_field(&mut seq, PSS_SALT_LEN_TAG)) {
+    Some(mut field) => {
+      params.salt_len = SaltLength(try!(field.read_uint()));
+      if !field.is_empty() { return Err(DecodingError) }
+    },
+    None => {}
+  }
+  match try!(read_tagged_field(&mut seq, PSS_TRAILER_TAG)) {
+    Some(mut field) => {
+      // Only the 0xbc trailer is defined
+      if try!(field.read_uint()) != PSS_TRAILER_FIELD_BC { return Err(InvalidKey) }
+      if !field.is_empty() { return Err(DecodingError) }
+    },
+    None => {}
+  }
+  if !seq.is_empty() { return Err(DecodingError) }
+  Ok(params)
+}
+
+// Reads an AlgorithmIdentifier and checks that it names an RSA key
+fn read_algorithm(reader: &mut DerReader) -> RsaResult<KeyAlgorithmT> {
+  let mut alg = try!(reader.read_sequence());
+  let oid = try!(alg.read_tlv(TAG_OID));
+  if oid == RSA_ENCRYPTION_OID.as_slice() {
+    try!(read_null_params(&mut alg));
+    Ok(RSAEncryption)
+  } else if oid == RSASSA_PSS_OID.as_slice() {
+    // Without parameters the key isn't restricted to particular PSS parameters
+    if alg.is_empty() { return Ok(RSASSAPSS(None)) }
+    let params = try!(read_pss_params(&mut alg));
+    if !alg.is_empty() { return Err(DecodingError) }
+    Ok(RSASSAPSS(Some(params)))
+  } else {
+    Err(InvalidKey)
+  }
+}
+
+impl PublicKey {
+  /// DER encoding of the X.509 SubjectPublicKeyInfo structure for rsaEncryption
+  pub fn to_spki_der(&self) -> ~[u8] {
+    self.to_spki_der_with_algorithm(&RSAEncryption)
+  }
+
+  /// DER encoding of the X.509 SubjectPublicKeyInfo structure for the given algorithm
+  pub fn to_spki_der_with_algorithm(&self, alg: &KeyAlgorithmT) -> ~[u8] {
+    encode_sequence([encode_algorithm(alg), encode_bit_string(self.to_pkcs1_der())])
+  }
+
+  /// Public key from the DER encoding of an X.509 SubjectPublicKeyInfo structure
+  /// for either rsaEncryption or RSASSA-PSS
+  pub fn from_spki_der(der: &[u8]) -> RsaResult<PublicKey> {
+    let (key, _) = try!(PublicKey::from_spki_der_with_algorithm(der));
+    Ok(key)
+  }
+
+  /// Public key and the algorithm it's marked for from the DER encoding of an
+  /// X.509 SubjectPublicKeyInfo structure
+  pub fn from_spki_der_with_algorithm(der: &[u8]) -> RsaResult<(PublicKey, KeyAlgorithmT)> {
+    let mut seq = try!(read_outer_sequence(der));
+    let alg = try!(read_algorithm(&mut seq));
+    let key = try!(seq.read_bit_string());
+    if !seq.is_empty() { return Err(DecodingError) }
+    let key = try!(PublicKey::from_pkcs1_der(key));
+    Ok((key, alg))
+  }
+}
+
+impl PrivateKey {
+  /// DER encoding of the PKCS#8 PrivateKeyInfo structure for rsaEncryption
+  /// Fails with `InvalidKey` if the key doesn't know its prime factors
+  pub fn to_pkcs8_der(&self) -> RsaResult<~[u8]> {
+    self.to_pkcs8_der_with_algorithm(&RSAEncryption)
+  }
+
+  /// DER encoding of the PKCS#8 PrivateKeyInfo structure for the given algorithm
+  pub fn to_pkcs8_der_with_algorithm(&self, alg: &KeyAlgorithmT) -> RsaResult<~[u8]> {
+    let key = try!(self.to_pkcs1_der());
+    let version: BigUint = Zero::zero();
+    Ok(encode_sequence([encode_integer(&version), encode_algorithm(alg),
+                        encode_tlv(TAG_OCTET_STRING, key)]))
+  }
+
+  /// Private key from the DER encoding of a PKCS#8 PrivateKeyInfo structure
+  /// for either rsaEncryption or RSASSA-PSS
+  pub fn from_pkcs8_der(der: &[u8]) -> RsaResult<PrivateKey> {
+    let (key, _) = try!(PrivateKey::from_pkcs8_der_with_algorithm(der));
+    Ok(key)
+  }
+
+  /// Private key and the algorithm it's marked for from the DER encoding of a
+  /// PKCS#8 PrivateKeyInfo structure
+  /// Attributes and the public key of the newer OneAsymmetricKey format are ignored
+  pub fn from_pkcs8_der_with_algorithm(der: &[u8]) -> RsaResult<(PrivateKey, KeyAlgorithmT)> {
+    let mut seq = try!(read_outer_sequence(der));
+    // Version 1 is the OneAsymmetricKey format from RFC 5958
+    let version = try!(seq.read_integer());
+    let one: BigUint = One::one();
+    if version > one { return Err(DecodingError) }
+    let alg = try!(read_algorithm(&mut seq));
+    let key = try!(seq.read_tlv(TAG_OCTET_STRING));
+
+    // Only context-specific elements may follow the key
+    loop {
+      match seq.peek_tag() {
+        Some(tag) if tag & 0xc0 == 0x80 => { try!(seq.read_tlv(tag)); },
+        Some(_)                         => return Err(DecodingError),
+        None                            => break
+      }
+    }
+    let key = try!(PrivateKey::from_pkcs1_der(key));
+    Ok((key, alg))
+  }
+}
+
+#[cfg(test)]
+mod test_pkcs8 {
+  use super::{RSAEncryption, RSASSAPSS};
+  use super::super::{PublicKey, PrivateKey, PSSParams, SaltLength, KeySize, Exponent, gen_keys,
+                     DecodingError, InvalidKey};
+  use hash::{SHA1, SHA256, SHA512};
+  use bignum::ToBigUint;
+  use serialize::hex::{ToHex, FromHex};
+
+  // SubjectPublicKeyInfo of the 1024-bit OpenSSL test key, from `openssl pkey -pubout -outform DER`
+  static OPENSSL_SPKI_DER: &'static str =
+    "30819f300d06092a864886f70d010101050003818d0030818902818100ae36ca\
+     6d0203a4524ee1f7649bd628e19256ae338011fa9af93b19b1ccc2b957ef0586\
+     0c2ad0cb454f9d876d73a8b73d57ae51e0d6b875d66d268433457da6fa069b99\
+     83c4e2e747f5b513b28e09c7f654eb0fa5c046804d3f5d1733721e0c730379f1\
+     cf57c424740715b781984d286a8724d12f41747382d11f6e6b309c65bf020301\
+     0001";
+  // SubjectPublicKeyInfo of a key restricted to PSS with SHA-256 and a 32 byte salt, from
+  // `openssl genpkey -algorithm RSA-PSS -pkeyopt rsa_pss_keygen_md:sha256 ...`
+  static OPENSSL_PSS_SPKI_DER: &'static str =
+    "3081d3304106092a864886f70d01010a3034a00f300d06096086480165030402\
+     010500a11c301a06092a864886f70d010108300d060960864801650304020105\
+     00a20302012003818d0030818902818100c722e3119e0d6332f43d2ab2154f0c\
+     941a6909e5780e5091df4b31745fd3fe89b1a1c945709d75a8f7ddd6360de358\
+     0f01fd784e7f5cde47c66645ec65f915c1c3e436e26e899ce814e12653e6dea9\
+     9647199ae74b1c01f2ddc643c17173472087005ac0068f5330bc3a72bd654159\
+     48c0e123c452c33fa54fe12134c4c4b4fd0203010001";
+
+  #[test]
+  fn test_spki_der() {
+    let der = OPENSSL_SPKI_DER.from_hex().unwrap();
+    let public = PublicKey::from_spki_der(der).unwrap();
+    assert_eq!(public.e, 65537u.to_biguint().unwrap());
+    assert_eq!(public.key_size, 1024);
+    assert_eq!(public.to_spki_der(), der);
+  }
+
+  #[test]
+  fn test_spki_der_rejects_other_algorithms() {
+    // The last byte of the OID changed to 1.2.840.113549.1.1.11, sha256WithRSAEncryption
+    let mut der = OPENSSL_SPKI_DER.from_hex().unwrap();
+    der[15] = 0x0b;
+    assert_eq!(PublicKey::from_spki_der(der).err(), Some(InvalidKey));
+  }
+
+  #[test]
+  fn test_pkcs8_der() {
+    let (public, private) = gen_keys(KeySize(512), Exponent(65537u)).unwrap();
+    let der = private.to_pkcs8_der().unwrap();
+    let decoded = PrivateKey::from_pkcs8_der(der).unwrap();
+    assert_eq!(decoded.to_pkcs1_der().unwrap(), private.to_pkcs1_der().unwrap());
+    assert_eq!(decoded.public_key().to_pkcs1_der(), public.to_pkcs1_der());
+
+    // An unwrapped PKCS#1 key isn't a PrivateKeyInfo
+    assert_eq!(PrivateKey::from_pkcs8_der(private.to_pkcs1_der().unwrap()).err(), Some(DecodingError));
+  }
+
+  #[test]
+  fn test_pss_spki_der() {
+    let der = OPENSSL_PSS_SPKI_DER.from_hex().unwrap();
+    let (public, alg) = PublicKey::from_spki_der_with_algorithm(der).unwrap();
+    assert_eq!(alg, RSASSAPSS(Some(PSSParams::new(SHA256))));
+    assert_eq!(public.to_spki_der_with_algorithm(&alg), der);
+    assert_eq!(PublicKey::from_spki_der(der).unwrap().n, public.n);
+
+    // Without parameters the algorithm identifier is just the OID
+    let unrestricted = public.to_spki_der_with_algorithm(&RSASSAPSS(None));
+    assert_eq!(unrestricted.slice(3, 16).to_hex(), ~"300b06092a864886f70d01010a");
+    let (_, alg) = PublicKey::from_spki_der_with_algorithm(unrestricted).unwrap();
+    assert_eq!(alg, RSASSAPSS(None));
+  }
+
+  #[test]
+  fn test_pss_params_defaults() {
+    let (public, private) = gen_keys(KeySize(512), Exponent(65537u)).unwrap();
+    // SHA-1 with a 20 byte salt is the default, so nothing is encoded
+    let params = PSSParams{ hash: SHA1, mgf_hash: SHA1, salt_len: SaltLength(20) };
+    let der = public.to_spki_der_with_algorithm(&RSASSAPSS(Some(params.clone())));
+    assert_eq!(der.slice(2, 19).to_hex(), ~"300f06092a864886f70d01010a3000");
+    let (_, alg) = PublicKey::from_spki_der_with_algorithm(der).unwrap();
+    assert_eq!(alg, RSASSAPSS(Some(params)));
+
+    let params = PSSParams{ hash: SHA512, mgf_hash: SHA1, salt_len: SaltLength(0) };
+    let alg = RSASSAPSS(Some(params));
+    let der = private.to_pkcs8_der_with_algorithm(&alg).unwrap();
+    let (decoded, decoded_alg) = PrivateKey::from_pkcs8_der_with_algorithm(der).unwrap();
+    assert_eq!(decoded_alg, alg);
+    assert_eq!(decoded.to_pkcs1_der().unwrap(), private.to_pkcs1_der().unwrap());
+    let (_, alg) = PrivateKey::from_pkcs8_der_with_algorithm(private.to_pkcs8_der().unwrap()).unwrap();
+    assert_eq!(alg, RSAEncryption);
+  }
+}
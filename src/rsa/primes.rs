@@ -1,7 +1,7 @@
 use std::num::{Zero, One};
 use std::iter::{count, range_step_inclusive};
 use std::slice;
-use rand::task_rng;
+use rand::{Rng, OSRng, task_rng};
 use bignum::{BigUint, RandBigInt, ToBigUint, BigInt, ToBigInt};
 use num::Integer;
 
@@ -67,8 +67,9 @@ fn rewrite(n: &BigUint) -> (BigUint, BigUint) {
   (s, d)
 }
 
-// Rabin-Miller until probability of false-positive is < 2^-128
-fn rabin_miller(candidate: &BigUint) -> bool {
+// Rabin-Miller with `rounds` bases drawn from `rng`
+// A composite passes with probability < 4^-rounds
+fn rabin_miller<R: Rng>(rng: &mut R, candidate: &BigUint, rounds: uint) -> bool {
   let zero: BigUint = Zero::zero();
   let one: BigUint = One::one();
   let two = one + one;
@@ -78,10 +79,8 @@ fn rabin_miller(candidate: &BigUint) -> bool {
   if candidate.is_even() { return false }
 
   let (s, d) = rewrite(&(candidate - one));
-  // Probability of false-positive is 2^-k
-  let mut k = 0;
-  while k < 128 {
-    let basis = task_rng().gen_biguint_range(&two, candidate);
+  for _ in range(0, rounds) {
+    let basis = rng.gen_biguint_range(&two, candidate);
     let mut v = mod_exp(&basis, &d, candidate);
     if v != one && v != (candidate - one) {
       let mut i = zero.clone();
@@ -95,12 +94,23 @@ fn rabin_miller(candidate: &BigUint) -> bool {
         i = i + one;
       }
     }
-    k += 2;
   }
   true
 }
 
 pub fn is_prime(candidate: &BigUint) -> bool {
+  is_prime_with_rng(&mut task_rng(), candidate)
+}
+
+/// Like `is_prime`, with the Rabin-Miller bases drawn from `rng`
+pub fn is_prime_with_rng<R: Rng>(rng: &mut R, candidate: &BigUint) -> bool {
+  // Probability of false-positive is < 2^-128
+  is_probable_prime(rng, candidate, 64)
+}
+
+/// Trial division by the primes below 1000, then `rounds` rounds of Rabin-Miller
+/// with bases drawn from `rng`
+pub fn is_probable_prime<R: Rng>(rng: &mut R, candidate: &BigUint, rounds: uint) -> bool {
   for p in small_primes(1000).move_iter() {
     let bigp = &p.to_biguint().unwrap();
     if candidate == bigp {
@@ -109,32 +119,98 @@ pub fn is_prime(candidate: &BigUint) -> bool {
       return false;
     }
   }
-  rabin_miller(candidate)
+  rabin_miller(rng, candidate, rounds)
 }
 
+/// A random prime of exactly `size` bits, with the top two bits set, from the
+/// operating system's random number generator
+/// The product of two such primes has exactly as many bits as the two together
 pub fn big_prime(size: uint) -> BigUint {
+  big_prime_with_rng(&mut OSRng::new(), size)
+}
+
+/// Like `big_prime`, with the search starting from a number drawn from `rng`
+/// The primality tests draw from `rng` too, so a deterministic `rng` such as
+/// `drbg::HmacDrbg` always gives the same prime
+pub fn big_prime_with_rng<R: Rng>(rng: &mut R, size: uint) -> BigUint {
+  assert!(size >= 2);
   let one: BigUint = One::one();
   let two = one + one;
+  let top_bits = (one + two) << (size - 2);
 
-  let mut rng = task_rng();
-  let mut candidate = rng.gen_biguint(size);
-  if candidate.is_even() {
-    candidate = candidate + one;
-  }
-  while !is_prime(&candidate) {
-    candidate = candidate + two;
+  loop {
+    let mut candidate = rng.gen_biguint(size) | top_bits;
+    if candidate.is_even() {
+      candidate = candidate + one;
+    }
+    // Start again from a new number if the search runs past `size` bits
+    while candidate.bits() == size {
+      if is_prime_with_rng(rng, &candidate) { return candidate }
+      candidate = candidate + two;
+    }
   }
-  candidate
 }
 
 /// An prime suitable for RSA with exponent `e`
-/// The prime `p` - 1 can't be a multiple of `e`
+/// `p` - 1 must be coprime to `e`, which must be odd
 pub fn rsa_prime(size: uint, e: &BigUint) -> BigUint {
+  rsa_prime_with_rng(&mut OSRng::new(), size, e)
+}
+
+/// Like `rsa_prime`, with the primes drawn from `rng`
+pub fn rsa_prime_with_rng<R: Rng>(rng: &mut R, size: uint, e: &BigUint) -> BigUint {
+  // p - 1 is even, so no prime would do for an even `e`
+  assert!(e.is_odd());
   let one: BigUint = One::one();
   loop {
-    let p = big_prime(size);
-    if p.modulus(e) != one { return p }
+    let p = big_prime_with_rng(rng, size);
+    if (p - one).gcd(e) == one { return p }
+  }
+}
+
+// Minimum Rabin-Miller rounds for the primes of an `nlen`-bit modulus, from
+// FIPS 186-5 Table B.1
+fn fips186_rounds(nlen: uint) -> uint {
+  if nlen < 3072 { 5 } else { 4 }
+}
+
+/// A prime for an `nlen`-bit RSA modulus with exponent `e`, following FIPS 186-5
+/// Appendix A.1.3: p >= sqrt(2) * 2^(nlen/2 - 1) and gcd(p - 1, e) = 1
+/// When generating the second prime, `first` is the first one, and the two
+/// will differ by more than 2^(nlen/2 - 100)
+/// None if no prime was found within the allowed number of candidates
+pub fn fips186_prime<R: Rng>(rng: &mut R, nlen: uint, e: &BigUint, first: Option<&BigUint>) -> Option<BigUint> {
+  let one: BigUint = One::one();
+  let half = nlen / 2;
+  // p < sqrt(2) * 2^(nlen/2 - 1) exactly when p^2 < 2^(nlen - 1)
+  let min_square = one << (nlen - 1);
+  let min_distance = one << (half - 100);
+  let max_tests = match first { None => 5 * half, Some(_) => 10 * half };
+  let rounds = fips186_rounds(nlen);
+
+  let mut tests = 0;
+  while tests < max_tests {
+    let mut candidate = rng.gen_biguint(half);
+    if candidate.is_even() {
+      candidate = candidate + one;
+    }
+    match first {
+      Some(p) if distance(p, &candidate) <= min_distance => continue,
+      _                                                   => ()
+    }
+    if candidate * candidate < min_square { continue }
+
+    if (candidate - one).gcd(e) == one && is_probable_prime(rng, &candidate, rounds) {
+      return Some(candidate)
+    }
+    tests += 1;
   }
+  None
+}
+
+// |a - b|
+fn distance(a: &BigUint, b: &BigUint) -> BigUint {
+  if a > b { a - *b } else { b - *a }
 }
 
 /// Extended Euclidean GCD algorithm
@@ -176,8 +252,10 @@ pub fn invmod(x: &BigUint, modulus: &BigUint) -> Option<BigUint> {
 
 #[cfg(test)]
 mod test_primes {
-  use super::{small_primes, mod_exp, is_prime, big_prime, rsa_prime, invmod};
+  use super::{small_primes, mod_exp, is_prime, big_prime, big_prime_with_rng, rsa_prime, invmod};
   use bignum::{BigUint, ToBigUint};
+  use drbg::HmacDrbg;
+  use hash::SHA256;
   use std::from_str::FromStr;
   use std::num::{One};
 
@@ -238,10 +316,20 @@ mod test_primes {
     let size = 1024;
     let p = big_prime(size);
 
-    assert!(p.bits() >= size - 1);
+    assert_eq!(p.bits(), size);
+    assert!(p >= 3u.to_biguint().unwrap() << (size - 2));
     assert!(is_prime(&p));
   }
 
+  // Checked against a Python port of the search and of HMAC_DRBG
+  #[test]
+  fn test_big_prime_with_rng() {
+    let seed = bytes!("rsa key generation test seed....");
+    let p = big_prime_with_rng(&mut HmacDrbg::new(SHA256, seed, [], []), 64);
+    assert_eq!(p, 0xf280c5151b7b217du64.to_biguint().unwrap());
+    assert_eq!(big_prime_with_rng(&mut HmacDrbg::new(SHA256, seed, [], []), 64), p);
+  }
+
   #[test]
   fn test_rsa_prime() {
     let one = 1u.to_biguint().unwrap();